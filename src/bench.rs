@@ -0,0 +1,120 @@
+// Benchmark of the tiny Salewski chess engine
+// cargo run --release -- bench [depth] [--params params.toml]
+//
+// Searches a fixed set of positions to a fixed depth, each with a new game, so the results do not
+// depend on earlier searches. Prints the nodes and time per position, the totals with the nodes
+// per second and the TT hit and cutoff rates, and a signature of the node counts and best moves.
+// The signature changes only when the search itself changes, the time and NPS compare the speed
+// of two builds.
+
+use crate::engine;
+use std::time::Instant;
+
+const DEFAULT_DEPTH: usize = 7;
+
+const POSITIONS: [&str; 10] = [
+    engine::START_FEN,
+    "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+    "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+    "2r3k1/pp3ppp/2n5/3p4/3P4/2N5/PP3PPP/2R3K1 w - - 0 1",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "8/8/4k3/3p4/3P4/4K3/8/8 w - - 0 1",
+    "6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1",
+    "r1b1k2r/ppppnppp/2n2q2/2b5/3NP3/2P1B3/PP3PPP/RN1QKB1R w KQkq - 0 1",
+];
+
+pub fn run(args: &[String]) {
+    let depth = match args.first().filter(|a| !a.starts_with("--")) {
+        None => DEFAULT_DEPTH,
+        Some(d) => match d.parse() {
+            Ok(d) if d > 0 => d,
+            _ => {
+                eprintln!("usage: bench [depth] [--params file]");
+                return;
+            }
+        },
+    };
+    let params = match args.iter().position(|a| a == "--params") {
+        None => None,
+        Some(i) => match args.get(i + 1).map(|p| engine::load_params(p)) {
+            Some(Ok(p)) => Some(p),
+            Some(Err(e)) => {
+                eprintln!("{}", e);
+                return;
+            }
+            None => {
+                eprintln!("usage: bench [depth] [--params file]");
+                return;
+            }
+        },
+    };
+    let start = Instant::now();
+    let (total, signature) = match search(depth, params.as_ref()) {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    let elapsed = start.elapsed().as_secs_f64();
+    println!("depth:     {}", depth);
+    println!("nodes:     {}", total.nodes);
+    println!("time:      {:.2} s", elapsed);
+    println!("nps:       {:.0}", total.nodes as f64 / elapsed.max(1e-6));
+    println!("tt hits:   {:.1}%", total.tt_hit_rate() * 100.0);
+    println!("cutoffs:   {:.1}%", total.cutoff_rate() * 100.0);
+    println!("signature: {:016x}", signature);
+}
+
+// the statistics of all positions and the signature
+fn search(
+    depth: usize,
+    params: Option<&engine::EvalParams>,
+) -> Result<(engine::SearchStats, u64), String> {
+    let limits = engine::SearchLimits {
+        max_depth: depth,
+        ..Default::default()
+    };
+    let mut total = engine::SearchStats::default();
+    let mut signature: u64 = 0xcbf29ce484222325; // FNV-1a
+    for (i, fen) in POSITIONS.iter().enumerate() {
+        let mut g = engine::new_game();
+        g.verbose = false;
+        if let Some(p) = params {
+            engine::set_params(&mut g, p.clone());
+        }
+        engine::set_fen(&mut g, fen).map_err(|e| format!("position {}: {}", i + 1, e))?;
+        let t = Instant::now();
+        let r = engine::reply(&mut g, &limits).map_err(|e| format!("position {}: {}", i + 1, e))?;
+        let elapsed = t.elapsed().as_secs_f64();
+        let nodes = r.stats.nodes;
+        total += r.stats;
+        let best = engine::move_to_uci(&r.best);
+        for byte in nodes.to_le_bytes().iter().chain(best.as_bytes()) {
+            signature = (signature ^ *byte as u64).wrapping_mul(0x100000001b3);
+        }
+        println!(
+            "position {:>2}: {:<6} {:>10} nodes {:>8.2} s",
+            i + 1,
+            best,
+            nodes,
+            elapsed
+        );
+    }
+    Ok((total, signature))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signature_is_reproducible() {
+        let (total, signature) = search(3, None).unwrap();
+        assert!(total.nodes > 0);
+        let (again, again_signature) = search(3, None).unwrap();
+        assert_eq!((again.nodes, again_signature), (total.nodes, signature));
+    }
+}
@@ -11,10 +11,8 @@
 // create a real GUI: well, at least we have an egui version with threading now
 // avoid global variables, make board a parameter of abeta() // Done in Rust port
 // make transposition table size configurable?
-// make aggression configurable
 // make aggression depending on winning/loosing
-// add optional random noise
-// and of course much more: setting up a position, saving/loading games, undo, opening lib, ...
+// and of course much more: setting up a position, saving/loading games, undo, ...
 
 // #![allow(dead_code)]
 // #![allow(non_camel_case_names)]
@@ -24,12 +22,35 @@
 //use bitintr::Popcnt;
 use core::ops::Range;
 use num_traits::sign::signum;
-use std::cmp::max;
+use std::cmp::{max, min};
 use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+mod book;
+mod chess960;
+mod king;
+mod mate;
+mod ordering;
+mod params;
+mod pawns;
+mod record;
+mod see;
+mod skill;
+mod syzygy;
+mod variant;
+pub use book::{open_book, Book, BookSelection};
+pub use chess960::{castling_moves, chess960_fen};
+pub use mate::solve_mate;
+pub use params::{load_params, set_params, EvalParams};
+pub use record::{game_record, load_record, GameRecord};
+pub use skill::{skill_level_from_elo, MAX_ELO, MAX_SKILL_LEVEL, MIN_ELO};
+pub use syzygy::Tablebases;
+pub use variant::{set_variant, Variant};
+
 // ### our own primitive bitset type
 #[derive(Copy, Clone, Debug)]
 struct BitSet(u64);
@@ -103,11 +124,6 @@ impl BitSet {
         self.0 == other.0
     }
     */
-
-    // Checks if two BitSets are disjoint (no bits in common)
-    fn is_disjoint(&self, other: &BitSet) -> bool {
-        (self.0 & other.0) == 0
-    }
 }
 
 impl Default for BitSet {
@@ -125,26 +141,36 @@ fn _print_variable_type<K>(_: &K) {
 //#[derive(Default)]
 //#[derive(Clone)]
 pub struct Game {
-    table_put: i64, // some fields like this are only for statistics and debugging
-    table_col: i64,
-    max_cup: i64,
-    ab_call: i64,
-    score_hash_succ: i64,
-    floor_hash_succ: i64,
-    hash_succ: i64,
-    null_move_succ_1: i64,
-    null_move_succ_2: i64,
-    re_eval_skip: i64,
-    max_delta_len: i64,
-    is_endgame: bool,
+    pub stats: SearchStats,             // of the current or last search of reply()
+    node_limit: u64,                    // see SearchLimits
+    pub stop: Arc<AtomicBool>,          // set from another thread to terminate the search
+    can_stop: bool,                     // the first iteration of reply() is never terminated
+    verifying: bool, // in a null move verification search of abeta(), which does no null moves
+    root_exclude: Vec<(i8, i8, i8)>, // root moves (si, di, promote_to) skipped by abeta(), for MultiPV
+    root_scores: Vec<i64>,           // and their scores, for the rank of the current line
+    pub progress: Option<ProgressFn>, // called after each iteration of reply()
+    pub verbose: bool,               // print search details to stdout
+    pub book: Option<Book>,          // consulted by reply() before searching
+    pub tablebases: Option<Tablebases>, // probed at the root and in abeta() when few pieces remain
+    pub skill_level: u8, // 1 ..= MAX_SKILL_LEVEL, lower levels play weaker moves on purpose
+    pub contempt: [i16; 2], // White, Black: a draw scores -contempt for the side to move in reply()
+    pub aggression: [i16; 2], // White, Black: weight of the king attack terms in percent
+    root_color: Color,   // the side to move in reply(), for the contempt
+    pub params: EvalParams, // change with set_params()
+    figure_value: [i16; KING_ID as usize + 1], // piece values of params, indexed by the absolute FigureID
+    mop_up: bool, // pawnless ending, with the hunting tables of setup_mop_up() in freedom_eg
     start_time: std::time::Instant,
     tt: Vec<TTE>,
+    pawn_tt: Vec<pawns::PawnEntry>,
     debug_list: Vec<String>,
     history: HashMap<BitBuffer192, i32>,
     board: Board,
     has_moved: HasMoved,
     move_chain: [i8; 64], // large enough to avoid IF index-in-range test
-    freedom: Freedom,
+    killers: ordering::Killers,
+    butterfly: ordering::Butterfly, // history counts of quiet moves with a beta cutoff
+    freedom: Freedom,               // middlegame
+    freedom_eg: Freedom,            // endgame, interpolated with freedom by the game phase
     pawn_path: [Path; 2],
     knight_path: Path,
     bishop_path: Path,
@@ -152,6 +178,7 @@ pub struct Game {
     king_path: Path,
     to_100: u8,
     pub secs_per_move: f32,
+    pub clock: Option<Clock>, // when set, the time for each move is taken from the game clock
     time_0: std::time::Duration,
     _time_1: std::time::Duration,
     time_2: std::time::Duration,
@@ -159,6 +186,155 @@ pub struct Game {
     time_4: std::time::Duration,
     pub move_counter: u16,
     pjm: i8,
+    castling: chess960::CastlingSquares, // start squares of kings and rooks, set by set_fen()
+    pub chess960: bool, // castlings are encoded as the king capturing its own rook, see chess960.rs
+    pub variant: Variant, // change with set_variant()
+    pub checks: [u8; 2], // White, Black: the checks given, for Three-check
+    start_fen: String,  // the position of reset_game() or set_fen(), for game_record()
+    played: Vec<Move>,  // the moves of do_move() and do_move_promote() since then
+}
+
+// remaining time, increment and moves until the next time control, as seen by the side to move.
+// Index 0 is White, 1 is Black, as for move_counter % 2.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Clock {
+    pub remaining: [f32; 2], // seconds
+    pub increment: [f32; 2], // seconds added after each move
+    pub moves_to_go: u16,    // 0 for sudden death
+}
+
+// limits for a single call of reply(). The default is a normal search with time management.
+// Depth, node and mate limits search without time limits, unless a movetime is given as well.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct SearchLimits {
+    pub max_depth: usize, // 0 for MAX_DEPTH
+    pub max_nodes: u64,   // 0 for no limit
+    pub movetime: f32,    // exact time in seconds, 0.0 to use the clock or secs_per_move
+    pub infinite: bool,   // no time limits at all
+    pub mate: usize,      // search for a mate in that many moves, 0 for a normal search
+}
+
+impl SearchLimits {
+    fn timed(&self) -> bool {
+        self.movetime == 0.0
+            && !self.infinite
+            && self.max_depth == 0
+            && self.max_nodes == 0
+            && self.mate == 0
+    }
+}
+
+// errors of the public functions, which leave the game unchanged when they fail
+#[derive(Clone, Debug, PartialEq)]
+pub enum EngineError {
+    InvalidSquare(i64),  // a position not in 0..64
+    IllegalMove(String), // not legal in the current position
+    InvalidMove(String), // malformed or ambiguous move notation
+    NoLegalMove,         // checkmate, stalemate or a variant win, reply() has nothing to search
+    InvalidFen(String),  // with the reason
+    InvalidParams(String),
+}
+
+impl std::fmt::Display for EngineError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            EngineError::InvalidSquare(p) => write!(f, "invalid square {}", p),
+            EngineError::IllegalMove(m) => write!(f, "illegal move {}", m),
+            EngineError::InvalidMove(m) => write!(f, "invalid move {}", m),
+            EngineError::NoLegalMove => write!(f, "no legal move"),
+            EngineError::InvalidFen(e) => write!(f, "invalid FEN: {}", e),
+            EngineError::InvalidParams(e) => write!(f, "invalid parameters: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for EngineError {}
+
+// progress of reply(), reported after each completed iteration
+#[derive(Clone, Debug, Default)]
+pub struct SearchInfo {
+    pub depth: usize,
+    pub multi_pv: usize, // 1 for the best line
+    pub score: i64,
+    pub nodes: u64,
+    pub time: Duration,
+    pub pv: Vec<Move>,
+    pub stats: SearchStats, // of all iterations up to this one
+}
+
+// counters of reply(), from its start on. They are returned in SearchResult, passed with
+// SearchInfo during the search, and stay readable in g.stats.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SearchStats {
+    pub nodes: u64,            // abeta() calls, including the ones stopped by limits
+    pub ab_call: u64,          // abeta() calls which were not stopped
+    pub hash_succ: u64,        // positions found in the transposition table
+    pub score_hash_succ: u64,  // with an exact score for the requested depth
+    pub floor_hash_succ: u64,  // with a lower bound for a beta cutoff
+    pub table_put: u64,        // transposition table stores
+    pub table_col: u64,        // stores that found no free or less valuable entry
+    pub beta_cut: u64,         // beta cutoffs in the move loop of abeta()
+    pub null_move_cut: u64,    // beta cutoffs of the null move pruning
+    pub null_move_succ_1: u64, // quiescence search stand pat cutoffs by the static evaluation
+    pub null_move_succ_2: u64, // and by the evaluation with mobility and king safety
+    pub re_eval_skip: u64,     // moves not searched again, as their score from the TT is below beta
+    pub re_search: u64,        // iterations searched again, as the score left the aspiration window
+    pub max_cup: i64,          // the largest ply from the root, without the quiescence search
+    pub max_delta_len: i64,    // the largest difference of the move list lengths of two plies
+}
+
+impl SearchStats {
+    // the share of abeta() calls which found their position in the transposition table
+    pub fn tt_hit_rate(&self) -> f64 {
+        self.hash_succ as f64 / self.ab_call.max(1) as f64
+    }
+
+    // the share of abeta() calls which ended with a beta cutoff, from the TT or the search
+    pub fn cutoff_rate(&self) -> f64 {
+        (self.floor_hash_succ
+            + self.beta_cut
+            + self.null_move_cut
+            + self.null_move_succ_1
+            + self.null_move_succ_2) as f64
+            / self.ab_call.max(1) as f64
+    }
+}
+
+// sums the counters, for the statistics of several searches
+impl std::ops::AddAssign for SearchStats {
+    fn add_assign(&mut self, o: Self) {
+        self.nodes += o.nodes;
+        self.ab_call += o.ab_call;
+        self.hash_succ += o.hash_succ;
+        self.score_hash_succ += o.score_hash_succ;
+        self.floor_hash_succ += o.floor_hash_succ;
+        self.table_put += o.table_put;
+        self.table_col += o.table_col;
+        self.beta_cut += o.beta_cut;
+        self.null_move_cut += o.null_move_cut;
+        self.null_move_succ_1 += o.null_move_succ_1;
+        self.null_move_succ_2 += o.null_move_succ_2;
+        self.re_eval_skip += o.re_eval_skip;
+        self.re_search += o.re_search;
+        self.max_cup = max(self.max_cup, o.max_cup);
+        self.max_delta_len = max(self.max_delta_len, o.max_delta_len);
+    }
+}
+
+pub type ProgressFn = Box<dyn FnMut(&SearchInfo) + Send>;
+
+// the best move of reply(), with the statistics of its search
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SearchResult {
+    pub best: Move,
+    pub stats: SearchStats, // all zero for book and tablebase moves
+}
+
+// one line of a MultiPV search, see reply_multi_pv()
+#[derive(Clone, Debug, Default)]
+pub struct PvLine {
+    pub score: i64,
+    pub pv: Vec<Move>,
 }
 
 pub fn print_move_list(g: &Game) {
@@ -200,6 +376,17 @@ pub fn reset_game(g: &mut Game) {
     g.move_counter = 0;
     g.pjm = -1;
     g.has_moved = BitSet::new();
+    g.to_100 = 0;
+    g.butterfly = [[[0; 64]; 64]; 2];
+    g.castling = chess960::STANDARD_CASTLING;
+    g.chess960 = false;
+    g.checks = [0; 2];
+    g.start_fen = START_FEN.to_string();
+    g.played.clear();
+    if g.mop_up {
+        init_freedom(g);
+        g.mop_up = false;
+    }
 }
 
 pub fn new_game() -> Game {
@@ -223,31 +410,48 @@ pub fn new_game() -> Game {
     // Default::default() does not work, e.g. Duration has no default value!
     let mut g = Game {
         secs_per_move: 1.5,
+        castling: chess960::STANDARD_CASTLING,
+        chess960: false,
+        variant: Variant::Standard,
+        checks: [0; 2],
+        start_fen: START_FEN.to_string(),
+        played: Vec::new(),
+        clock: None,
         time_0: Duration::new(0, 0),
         _time_1: Duration::new(0, 0),
         time_2: Duration::new(0, 0),
         time_3: Duration::new(0, 0),
         time_4: Duration::new(0, 0),
-        table_put: 0,
-        table_col: 0,
-        max_cup: 0,
-        ab_call: 0,
-        score_hash_succ: 0,
-        floor_hash_succ: 0,
-        hash_succ: 0,
-        null_move_succ_1: 0,
-        null_move_succ_2: 0,
-        re_eval_skip: 0,
-        max_delta_len: 0,
-        is_endgame: false,
+        stats: Default::default(),
+        node_limit: u64::MAX,
+        stop: Arc::new(AtomicBool::new(false)),
+        can_stop: false,
+        verifying: false,
+        root_exclude: Vec::new(),
+        root_scores: Vec::new(),
+        progress: None,
+        verbose: true,
+        book: None,
+        tablebases: None,
+        skill_level: MAX_SKILL_LEVEL,
+        contempt: [0; 2],
+        aggression: [100; 2],
+        root_color: COLOR_WHITE,
+        params: EvalParams::default(),
+        figure_value: EvalParams::default().figure_values(),
+        mop_up: false,
         start_time: Instant::now(),
         tt: vec![Default::default(); TTE_SIZE],
+        pawn_tt: vec![Default::default(); pawns::PAWN_HASH_SIZE],
         debug_list: Vec::new(),
         history: HashMap::new(),
         board: SETUP,
         has_moved: BitSet::new(),
         move_chain: [0; 64],
+        killers: [[(-1, -1); 2]; 64],
+        butterfly: [[[0; 64]; 64]; 2],
         freedom: [[0; 64]; 13],
+        freedom_eg: [[0; 64]; 13],
         pawn_path: [[[Gnu {
             pos: 0,
             nxt_dir_idx: 0,
@@ -272,11 +476,7 @@ pub fn new_game() -> Game {
         move_counter: 0,
         pjm: -1,
     };
-    init_pawn(&mut g, COLOR_WHITE);
-    init_pawn(&mut g, COLOR_BLACK);
-    init_bishop(&mut g);
-    init_knight(&mut g);
-    init_king(&mut g);
+    init_freedom(&mut g);
     init_rook(&mut g);
 
     //set_board(&mut g, VOID_ID, BF, B8);
@@ -354,31 +554,11 @@ pub fn new_game() -> Game {
 }
 
 fn reset_statistics(g: &mut Game) {
-    g.table_put = 0;
-    g.table_col = 0;
-    g.max_cup = 0;
-    g.ab_call = 0;
-    g.score_hash_succ = 0;
-    g.floor_hash_succ = 0;
-    g.hash_succ = 0;
-    g.null_move_succ_1 = 0;
-    g.null_move_succ_2 = 0;
-    g.re_eval_skip = 0;
-    g.max_delta_len = 0;
+    g.stats = Default::default();
 }
 
 fn write_statistics(g: &Game) {
-    println!("ab_call: {}", g.ab_call);
-    println!("score_hash_succ: {}", g.score_hash_succ);
-    println!("floor_hash_succ: {}", g.floor_hash_succ);
-    println!("max_cup: {}", g.max_cup);
-    println!("hash_succ: {}", g.hash_succ);
-    println!("table_put: {}", g.table_put);
-    println!("table_col: {}", g.table_col);
-    println!("null_move_succ_1: {}", g.null_move_succ_1);
-    println!("null_move_succ_2: {}", g.null_move_succ_2);
-    println!("re_eval_skip: {}", g.re_eval_skip);
-    println!("max_delta_len: {}", g.max_delta_len);
+    println!("{:#?}", g.stats);
     println!("to_100: {}", g.to_100);
 }
 
@@ -443,25 +623,10 @@ const KING_DIRS: [i32; 8] = [N, O, S, W, NO, SO, NW, SW]; // KING_DIRS = BISHOP_
 // we try to keep all the values small to fit in two bytes
 const AB_INF: i16 = 32000; // more than the summed value of all pieces
 const VOID_VALUE: i16 = 0;
-const PAWN_VALUE: i16 = 100;
-const KNIGHT_VALUE: i16 = 300;
-const BISHOP_VALUE: i16 = 300;
-const ROOK_VALUE: i16 = 500;
-const QUEEN_VALUE: i16 = 900;
 pub const KING_VALUE: i16 = 18000; // more than the summed value of all other pieces
 pub const KING_VALUE_DIV_2: i16 = KING_VALUE / 2;
 pub const SURE_CHECKMATE: i16 = KING_VALUE / 2; // still more than the summed value of all other pieces, but less than value of a king
 
-const FIGURE_VALUE: [i16; KING_ID as usize + 1] = [
-    VOID_VALUE,
-    PAWN_VALUE,
-    KNIGHT_VALUE,
-    BISHOP_VALUE,
-    ROOK_VALUE,
-    QUEEN_VALUE,
-    KING_VALUE,
-];
-
 const SETUP: [i64; 64] = [
     W_ROOK, W_KNIGHT, W_BISHOP, W_KING, W_QUEEN, W_BISHOP, W_KNIGHT, W_ROOK, W_PAWN, W_PAWN,
     W_PAWN, W_PAWN, W_PAWN, W_PAWN, W_PAWN, W_PAWN, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
@@ -517,13 +682,6 @@ type FigureID = i64;
 pub type Board = [FigureID; 64];
 type Freedom = [[i16; 64]; 13]; // VOID_ID..KING_ID; Maybe we should call it happyness
 
-const WR0: usize = 0; // initial positions of King and Rook for castling tests
-const WK3: usize = 3;
-const WR7: usize = 7;
-const BR56: usize = 56;
-const BK59: usize = 59;
-const BR63: usize = 63;
-
 // type ChessSquare = i8; // range[0 .. 63];
 type ChessSquares = BitSet; // set[ChessSquare];
 type HasMoved = BitSet; //set[ChessSquare];
@@ -713,15 +871,9 @@ fn get_tte<'a>(g: &'a mut Game, key: BitBuffer192) -> isize {
     return -1;
 }
 
-fn debug_inc(x: &mut i64) {
-    if cfg!(feature = "salewskiChessDebug") {
-        *x += 1;
-    }
-}
-
 fn put_tte(g: &mut Game, key: BitBuffer192, mut res: HashResult, pri: i64, hash_pos: isize) {
     debug_assert!(g.tt.len() == TTE_SIZE);
-    debug_inc(&mut g.table_put);
+    g.stats.table_put += 1;
     if hash_pos >= 0 {
         res.pri = pri;
         g.tt[hash_pos as usize].res = res;
@@ -737,7 +889,7 @@ fn put_tte(g: &mut Game, key: BitBuffer192, mut res: HashResult, pri: i64, hash_
             return;
         }
     }
-    debug_inc(&mut g.table_col);
+    g.stats.table_col += 1;
 }
 
 const HASH_RESULT_ALL_ZERO: HashLine1 = [Guide1 {
@@ -889,7 +1041,11 @@ fn much_faster_write_to_bit_buffer(g: &Game, c: Color) -> BitBuffer192 {
 
 fn encode_board(g: &Game, c: Color) -> BitBuffer192 {
     //return simpleWriteToBitBuffer(g, c);
-    return much_faster_write_to_bit_buffer(g, c);
+    let mut result = much_faster_write_to_bit_buffer(g, c);
+    // the unused bytes 22 and 23 keep the checks of Three-check
+    result[22] = g.checks[0];
+    result[23] = g.checks[1];
+    result
 }
 
 fn off_board_64(dst: Position) -> bool {
@@ -968,10 +1124,14 @@ fn init_bishop(g: &mut Game) {
         }
         let mut nxt_dir_start = i;
         g.bishop_path[src as usize][i].pos = -1;
-        g.freedom[(ARRAY_BASE_6 + W_BISHOP) as usize][src as usize] = (i as i16 - 10) * 4; // range -12..12 // abs val is big enough, so exchange of a
-        g.freedom[(ARRAY_BASE_6 + W_QUEEN) as usize][src as usize] = (i as i16 - 10) * 4; // range -12..12 // pawn for very good position may occur
-        g.freedom[(ARRAY_BASE_6 + B_BISHOP) as usize][src as usize] = (i as i16 - 10) * 4;
-        g.freedom[(ARRAY_BASE_6 + B_QUEEN) as usize][src as usize] = (i as i16 - 10) * 4;
+        let f = params::mobility_term(i, g.params.bishop_mobility_base, g.params.bishop_mobility); // range -12..12 // abs val is big enough, so exchange of a
+        for t in [&mut g.freedom, &mut g.freedom_eg] {
+            // pawn for very good position may occur
+            t[(ARRAY_BASE_6 + W_BISHOP) as usize][src as usize] = f;
+            t[(ARRAY_BASE_6 + W_QUEEN) as usize][src as usize] = f;
+            t[(ARRAY_BASE_6 + B_BISHOP) as usize][src as usize] = f;
+            t[(ARRAY_BASE_6 + B_QUEEN) as usize][src as usize] = f;
+        }
         while i > 0 {
             i -= 1;
             let h = g.bishop_path[src as usize][i].nxt_dir_idx == -1;
@@ -994,8 +1154,11 @@ fn init_knight(g: &mut Game) {
             }
         }
         g.knight_path[src as usize][i].pos = -1;
-        g.freedom[(ARRAY_BASE_6 + W_KNIGHT) as usize][src as usize] = (i as i16 - 5) * 4; // range -12..12
-        g.freedom[(ARRAY_BASE_6 + B_KNIGHT) as usize][src as usize] = (i as i16 - 5) * 4;
+        let f = params::mobility_term(i, g.params.knight_mobility_base, g.params.knight_mobility); // range -12..12
+        for t in [&mut g.freedom, &mut g.freedom_eg] {
+            t[(ARRAY_BASE_6 + W_KNIGHT) as usize][src as usize] = f;
+            t[(ARRAY_BASE_6 + B_KNIGHT) as usize][src as usize] = f;
+        }
     }
 }
 
@@ -1010,16 +1173,33 @@ fn init_king(g: &mut Game) {
             }
         }
         g.king_path[src as usize][i].pos = -1;
-        if src == 0 || src == 7 || src == 56 || src == 63 {
-            g.freedom[(ARRAY_BASE_6 + W_KING) as usize][src as usize] = -16;
-            g.freedom[(ARRAY_BASE_6 + B_KING) as usize][src as usize] = -16;
-        }
+        let corner = if src == 0 || src == 7 || src == 56 || src == 63 {
+            g.params.king_corner
+        } else {
+            0
+        };
+        g.freedom[(ARRAY_BASE_6 + W_KING) as usize][src as usize] = corner;
+        g.freedom[(ARRAY_BASE_6 + B_KING) as usize][src as usize] = corner;
+        // in the endgame the king belongs to the center, 0 for the four center squares, 3 for the border
+        let center_distance = (max((2 * row(src) - 7).abs(), (2 * col(src) - 7).abs()) / 2) as i16;
+        g.freedom_eg[(ARRAY_BASE_6 + W_KING) as usize][src as usize] =
+            -center_distance * g.params.king_center_eg;
+        g.freedom_eg[(ARRAY_BASE_6 + B_KING) as usize][src as usize] =
+            -center_distance * g.params.king_center_eg;
     }
 }
 
+// the piece-square tables freedom and freedom_eg
+fn init_freedom(g: &mut Game) {
+    init_pawn(g, COLOR_WHITE);
+    init_pawn(g, COLOR_BLACK);
+    init_bishop(g);
+    init_knight(g);
+    init_king(g);
+}
+
 // the first two moves are possible captures or -1 if at the border of the board
 fn init_pawn(g: &mut Game, color: Color) {
-    const PS: [i16; 8] = [8, 4, 2, 0, 0, 0, 1, 0]; // +1 for pawn at start row, and promote pressure gain
     for src in POS_RANGE {
         let mut i = 0;
         for d in PAWN_DIRS_WHITE {
@@ -1037,15 +1217,19 @@ fn init_pawn(g: &mut Game, color: Color) {
     let pc = color as i64;
     for p in POS_RANGE {
         g.freedom[(ARRAY_BASE_6 + pc) as usize][p as usize] =
-            PS[rows_to_go(p as i8, color as i64) as usize];
+            g.params.pawn_rows_to_go[rows_to_go(p as i8, color as i64) as usize];
+        g.freedom_eg[(ARRAY_BASE_6 + pc) as usize][p as usize] =
+            g.params.pawn_rows_to_go_eg[rows_to_go(p as i8, color as i64) as usize];
     }
     // fixate outer pawns on start_row, mostly for initial move ordering
     let pawn_row = if color == COLOR_WHITE { B2 } else { B7 };
     for col in [BA, BB, BG, BH] {
-        g.freedom[(ARRAY_BASE_6 + pc) as usize][board_pos(col, pawn_row)] = 2; // fixed, try last
+        g.freedom[(ARRAY_BASE_6 + pc) as usize][board_pos(col, pawn_row)] =
+            g.params.pawn_outer_start; // fixed, try last
     }
     for col in [BD, BE] {
-        g.freedom[(ARRAY_BASE_6 + pc) as usize][board_pos(col, pawn_row)] = 0; // try first
+        g.freedom[(ARRAY_BASE_6 + pc) as usize][board_pos(col, pawn_row)] =
+            g.params.pawn_center_start; // try first
     }
 }
 
@@ -1267,36 +1451,73 @@ pub struct Move {
     state: State,
 }
 
+const PHASE_MAX: i32 = 256; // all pieces on the board, 0 for a pure pawn ending
+
+// queen, rooks, bishops and knights, for both colors
+fn piece_material(g: &Game) -> [i32; 2] {
+    let mut result = [0; 2];
+    for f in g.board {
+        if f.abs() > PAWN_ID && f.abs() < KING_ID {
+            result[(f < 0) as usize] += g.figure_value[f.unsigned_abs() as usize] as i32;
+        }
+    }
+    result
+}
+
+// of one side in the initial position
+fn full_piece_material(g: &Game) -> i32 {
+    let p = &g.params;
+    2 * (p.knight_value + p.bishop_value + p.rook_value) as i32 + p.queen_value as i32
+}
+
+// PHASE_MAX in the middlegame, decreasing to 0 as pieces are exchanged
+fn game_phase(g: &Game) -> i32 {
+    let m = piece_material(g);
+    let full = 2 * full_piece_material(g);
+    min(m[0] + m[1], full) * PHASE_MAX / full
+}
+
+// interpolation of middlegame and endgame values
+fn taper(mg: i32, eg: i32, phase: i32) -> i16 {
+    ((mg * phase + eg * (PHASE_MAX - phase)) / PHASE_MAX) as i16
+}
+
 // result is for White
-fn plain_evaluate_board(g: &Game) -> i16 {
+fn plain_evaluate_board(g: &Game, phase: i32) -> i16 {
     let mut result: i16 = 0;
+    let mut mg: i32 = 0;
+    let mut eg: i32 = 0;
     for (p, f) in g.board.iter().enumerate() {
         // if f != VOID_ID -- does not increase performance
-        result += (FIGURE_VALUE[f.abs() as usize] as i16 + g.freedom[(6 + *f) as usize][p as usize])
-            as i16
-            * (signum(*f)) as i16;
+        let sign = signum(*f);
+        result += g.figure_value[f.unsigned_abs() as usize] * sign as i16;
+        mg += (g.freedom[(6 + *f) as usize][p] as i64 * sign) as i32;
+        eg += (g.freedom_eg[(6 + *f) as usize][p] as i64 * sign) as i32;
     }
-    if g.has_moved.contains(WK3) {
-        result -= 4;
-    } else {
-        if g.has_moved.contains(WR0) {
-            result -= 2;
-        }
-        if g.has_moved.contains(WR7) {
-            result -= 2;
-        }
-    }
-    if g.has_moved.contains(BK59) {
-        result += 4;
-    } else {
-        if g.has_moved.contains(BR56) {
-            result += 2;
-        }
-        if g.has_moved.contains(BR63) {
-            result += 2;
+    // castling rights matter in the middlegame only
+    let mut castling: i16 = 0;
+    for (c, sign) in [(0, -1), (1, 1)] {
+        if g.has_moved.contains(g.castling.king[c]) {
+            castling += sign * g.params.king_moved_penalty;
+        } else {
+            for rook in g.castling.rook[c] {
+                if g.has_moved.contains(rook) {
+                    castling += sign * g.params.rook_moved_penalty;
+                }
+            }
         }
     }
-    result
+    mg += castling as i32;
+    result + taper(mg, eg, phase)
+}
+
+// result is for White
+fn evaluate_board(g: &mut Game) -> i16 {
+    let phase = game_phase(g);
+    plain_evaluate_board(g, phase)
+        + pawns::evaluate_pawns(g, phase)
+        + king::king_shelter(g)
+        + variant::evaluate(g)
 }
 
 /*
@@ -1472,17 +1693,36 @@ fn king_pos(g: &Game, c: Color) -> i8 {
 }
 
 const V_RATIO: i64 = 8;
+const NULL_MOVE: i8 = -1; // in move_chain, for the skipped turn of null move pruning
+pub const MAX_CONTEMPT: i16 = 100; // the contempt is in -MAX_CONTEMPT..=MAX_CONTEMPT
+pub const MAX_AGGRESSION: i16 = 200; // the aggression is in 0..=MAX_AGGRESSION percent
+
+// contempt and aggression for White and Black, stored scores of the old values are discarded
+pub fn set_style(g: &mut Game, contempt: [i16; 2], aggression: [i16; 2]) {
+    if contempt != g.contempt || aggression != g.aggression {
+        for el in &mut g.tt {
+            *el = Default::default();
+        }
+    }
+    g.contempt = contempt;
+    g.aggression = aggression;
+}
 
+// the score of a draw for color, the side to move at the root avoids draws with a positive contempt
+fn draw_score(g: &Game, color: Color) -> i64 {
+    let c = g.contempt[(g.root_color == COLOR_BLACK) as usize] as i64;
+    if color == g.root_color {
+        -c
+    } else {
+        c
+    }
+}
+
+// the other depth extends are switched by g.params.extend, see EvalParams
 const RANGE_EXTEND: bool = false; // depth extend based on distance of movement -- bad idea
 const SELECT_EXTEND: bool = false; // depth extend based on source and destination pieces
-const CASTLING_EXTEND: bool = true;
 const CAPTURE_EXTEND: bool = false; // depth extend for captures -- already covered by ddi array
-const EQUAL_CAPTURE_EXTEND: bool = true; // depth extend for captures of pieces with similar value -- might be a good idea
 const LARGE_CAPTURE_EXTEND: bool = false; // i.e. pawn captures knight -- extend makes no sense
-const PAWN_MARCH_EXTEND: bool = true; // successive pawn moves of a single pawn, to gain conversion to queen
-const CHECK_EXTEND: bool = true; // depth extend when we are in check (or queen is attacked)
-const PROMOTE_EXTEND: bool = true; // pawn promotion
-const NO_EXTEND_AT_ALL: bool = false; // avoid depth extends for now
 
 // for endgame, to get a correct value for "moves to mate"
 // "moves to mate" is calculated from score and value of cup counter
@@ -1542,18 +1782,36 @@ fn abeta(
         score: LOWEST_SCORE as i64,
         ..Default::default()
     };
-    if g.start_time.elapsed() > g.time_4 {
+    if g.start_time.elapsed() > g.time_4
+        || g.stats.nodes > g.node_limit
+        || (g.can_stop && g.stop.load(Ordering::Relaxed))
+    {
         return result; // invalid due to hard time contraints.
     }
+    g.stats.nodes += 1;
+    if cup > 0 && g.tablebases.is_some() {
+        if let Some((score, control)) = syzygy::probe_abeta(g, color, ep_pos, cup) {
+            result.score = score;
+            result.control = control;
+            result.state = STATE_PLAYING;
+            return result;
+        }
+    }
+    if cup > 0 && g.variant != Variant::Standard && variant::lost(g, color) {
+        // the last move won by the rules of the variant, scored as a checkmate
+        result.score = -KING_VALUE as i64 + cup - 1;
+        result.state = STATE_CHECKMATE;
+        return result;
+    }
     debug_assert!(alpha_0 < beta);
-    debug_inc(&mut g.ab_call);
+    g.stats.ab_call += 1;
     debug_assert!(MAX_DEPTH == 15);
     debug_assert!(V_RATIO == 8);
     let depth_0: usize = max(v_depth / V_RATIO, 0) as usize; // starting at depth_0 == 0 we do only captures
     debug_assert!(depth_0 <= MAX_DEPTH);
     let only_captures = depth_0 == 0;
     if depth_0 > 0 {
-        lift(&mut g.max_cup, cup);
+        lift(&mut g.stats.max_cup, cup);
     }
     debug_assert!(cup >= 0);
     debug_assert!(std::mem::size_of::<KK>() == 8);
@@ -1572,14 +1830,16 @@ fn abeta(
     let back: Board; // backup for debugging, so we can test if all our moves undo operations are correct
     back = g.board; // test board integrity
     let v_depth = v_depth - V_RATIO;
+    let exclude_root = cup == 0 && !g.root_exclude.is_empty(); // stored scores are for the full move list
     let encoded_board = encode_board(&g, color);
-    let hash_pos = get_tte(g, encoded_board);
+    let mut hash_pos = get_tte(g, encoded_board);
     if hash_pos >= 0 {
         hash_res = g.tt[hash_pos as usize].res.clone(); // no way to avoid the clone() here
                                                         // debug_assert!(hash_res.kks.len() > 0); // can be zero for checkmate or stalemate
                                                         // we have the list of moves, and maybe the exact score, or a possible beta cutoff
-        debug_inc(&mut g.hash_succ);
-        for i in (depth_0..(MAX_DEPTH + 1)).rev() {
+        g.stats.hash_succ += 1;
+        let lowest_used = if exclude_root { MAX_DEPTH + 1 } else { depth_0 };
+        for i in (lowest_used..(MAX_DEPTH + 1)).rev() {
             if hash_res.score[i].s != INVALID_SCORE {
                 // we have the exact score, so return it
                 if i == depth_0
@@ -1593,7 +1853,7 @@ fn abeta(
                     result.dst = hash_res.score[i].di as i64;
                     result.promote_to = hash_res.score[i].promote_to as i64;
                     result.state = hash_res.state;
-                    debug_inc(&mut g.score_hash_succ);
+                    g.stats.score_hash_succ += 1;
                     return result;
                 } else if pmq(hash_res.score[i].s as i64, -cup) >= beta {
                     // at least we can use the score for a beta cutoff
@@ -1604,7 +1864,7 @@ fn abeta(
             if pmq(hash_res.floor[i].s as i64, -cup) >= beta {
                 // a beta cutoff
                 result.score = beta;
-                debug_inc(&mut g.floor_hash_succ);
+                g.stats.floor_hash_succ += 1;
                 return result;
             }
         }
@@ -1639,10 +1899,10 @@ fn abeta(
     let mut evaluation: i16 = LOWEST_SCORE;
     if depth_0 == 0 {
         // null move estimation for quiescence search
-        evaluation = plain_evaluate_board(&g) * color as i16 - old_list_len as i16;
+        evaluation = evaluate_board(g) * color as i16 - old_list_len as i16;
         if evaluation as i64 >= beta {
             result.score = beta;
-            debug_inc(&mut g.null_move_succ_1);
+            g.stats.null_move_succ_1 += 1;
             return result;
         }
     }
@@ -1687,31 +1947,7 @@ fn abeta(
         }
         debug_assert!(COLOR_WHITE == 1 && COLOR_BLACK == -1);
         debug_assert!(COLOR_WHITE == color || COLOR_BLACK == color);
-        let sign = color;
-        let offset = (color == COLOR_BLACK) as usize * 56;
-        if color == COLOR_WHITE && g.board[3] == W_KING
-            || color == COLOR_BLACK && g.board[59] == B_KING
-        {
-            kk.df = VOID_ID as i8;
-            kk.sf = (W_KING * sign) as i8;
-            if g.board[offset + 0] == W_ROOK * sign
-                && g.board[offset + 1] == VOID_ID
-                && g.board[offset + 2] == VOID_ID
-            {
-                kk.di = offset as i8 + 1;
-                kk.si = offset as i8 + 3;
-                s.push(kk);
-            }
-            if g.board[offset + 7] == W_ROOK * sign
-                && g.board[offset + 4] == VOID_ID
-                && g.board[offset + 5] == VOID_ID
-                && g.board[offset + 6] == VOID_ID
-            {
-                kk.di = offset as i8 + 5;
-                kk.si = offset as i8 + 3;
-                s.push(kk);
-            }
-        }
+        chess960::walk_castlings(g, color, kk, &mut s);
 
         /*
         kk.df = VOID_ID as i8; // for all 4 types of castling
@@ -1752,6 +1988,11 @@ fn abeta(
             }
         }
         */
+        let freedom = if game_phase(g) < PHASE_MAX / 2 {
+            &g.freedom_eg
+        } else {
+            &g.freedom
+        };
         for el in &mut s {
             debug_assert!(g.board[el.si as usize] != VOID_ID);
             // guessed ratings of the moves
@@ -1763,10 +2004,16 @@ fn abeta(
                     debug_assert!(el.promote_to == 0);
                 }
             }
-            el.s = FIGURE_VALUE[el.promote_to.abs() as usize] + FIGURE_VALUE[el.df.abs() as usize]
-                - FIGURE_VALUE[el.sf.abs() as usize] / 2 * (el.df != 0) as i16
-                + g.freedom[(6 + el.sf) as usize][(0 + el.di) as usize]
-                - g.freedom[(6 + el.sf) as usize][(0 + el.si) as usize];
+            let exchange = if el.df != VOID_ID as i8 {
+                see::see(g, el.si, el.di)
+            } else {
+                0
+            };
+            el.s = g.figure_value[el.promote_to.abs() as usize]
+                + exchange
+                + freedom[(6 + el.sf) as usize][(0 + el.di) as usize]
+                - freedom[(6 + el.sf) as usize][(0 + el.si) as usize]
+                + ordering::quiet_bonus(g, color, cup, el);
         }
         let h = s.len();
         ixsort(&mut s, h);
@@ -1775,7 +2022,7 @@ fn abeta(
         hash_res.kks = s;
         debug_assert!(hash_res.kks.len() > 0);
     }
-    if CHECK_EXTEND && !hash_res.tested_for_check && depth_0 > 1 {
+    if g.params.extend.check && !hash_res.tested_for_check && depth_0 > 1 {
         hash_res.in_check = (hash_res.queen_pos >= 0
             && queen_in_check(&g, hash_res.queen_pos, color))
             || in_check(&g, hash_res.king_pos, color, false);
@@ -1785,18 +2032,81 @@ fn abeta(
 
     let hash_res_kks_len =
         (hash_res.kks.len() as i64 + attacs + hash_res.control.0.count_ones() as i64) as i16;
+    let sw = g.params.search.clone();
+    if sw.null_move
+        && depth_0 >= sw.null_move_min_depth as usize
+        && cup > 0
+        && !g.verifying
+        && g.move_chain[cup as usize + 1] != NULL_MOVE // no two null moves in a row
+        && beta.abs() < KING_VALUE_DIV_2 as i64
+        && piece_material(g)[(color == COLOR_BLACK) as usize] > 0 // zugzwang is common with only king and pawns
+        && !hash_res.kks.iter().any(|el| is_a_king(el.df)) // the move loop returns the king capture
+        && !(if hash_res.tested_for_check {
+            hash_res.in_check
+        } else {
+            in_check(g, hash_res.king_pos, color, false)
+        })
+        && evaluate_board(g) as i64 * color >= beta
+    {
+        // null move pruning: we skip our turn, if the opponent can not even then reach beta
+        // with a reduced depth search, a real move will fail high as well
+        let verify = sw.null_move_verify && game_phase(g) < PHASE_MAX / 2;
+        let r = sw.null_move_reduction as i64 + (depth_0 > 6) as i64;
+        g.move_chain[cup as usize + 2] = NULL_MOVE;
+        let m = abeta(
+            g,
+            opp_color(color),
+            v_depth - r * V_RATIO,
+            cup + 1,
+            -beta,
+            -beta + 1,
+            hash_res_kks_len as i64,
+            -1,
+        );
+        if m.score == LOWEST_SCORE as i64 {
+            // hard cut with invalid result
+            return result;
+        }
+        if -m.score >= beta {
+            let mut cut = true;
+            if verify {
+                // zugzwang is possible in the endgame, so search our real moves with the same reduction
+                g.verifying = true;
+                let v = abeta(
+                    g,
+                    color,
+                    v_depth + V_RATIO - r * V_RATIO,
+                    cup,
+                    beta - 1,
+                    beta,
+                    old_list_len,
+                    ep_pos,
+                );
+                g.verifying = false;
+                if v.score == LOWEST_SCORE as i64 {
+                    return result;
+                }
+                cut = v.score >= beta;
+                hash_pos = get_tte(g, encoded_board); // the verification search may have stored this position
+            }
+            if cut {
+                g.stats.null_move_cut += 1;
+                result.score = beta;
+                return result;
+            }
+        }
+    }
     if depth_0 == 0 {
         // more detailed null move estimation for quiescence search. NOTE: Take attacs into account?
         evaluation += hash_res_kks_len; // we may do a more fine grained board control evaluation?
-        if cfg!(feature = "salewskiChessDebug") {
-            lift(
-                &mut g.max_delta_len,
-                (hash_res.kks.len() as i64 - old_list_len).abs(),
-            );
-        }
+        evaluation += king::king_zone_attacks(g, color, &hash_res.control);
+        lift(
+            &mut g.stats.max_delta_len,
+            (hash_res.kks.len() as i64 - old_list_len).abs(),
+        );
         if evaluation as i64 >= beta {
             result.score = beta;
-            debug_inc(&mut g.null_move_succ_2);
+            g.stats.null_move_succ_2 += 1;
             return result;
         }
         lift(&mut alpha, evaluation as i64);
@@ -1805,7 +2115,7 @@ fn abeta(
     let mut hash_res_kks_high: usize = 0; // the number of newly evaluated positions, we sort only this range.
     result.score = evaluation as i64; // LOWEST_SCORE for depth_0 > 0
     debug_assert!(depth_0 == 0 || result.score == LOWEST_SCORE as i64);
-    debug_assert!(hash_res.score[depth_0].s == INVALID_SCORE);
+    debug_assert!(exclude_root || hash_res.score[depth_0].s == INVALID_SCORE);
     // debug_assert!(hash_res.kks.len() > 0); occurs in endgame?
     for el in &mut hash_res.kks {
         if el.s == IGNORE_MARKER_LOW_INT16 {
@@ -1819,14 +2129,23 @@ fn abeta(
             // skip non-captures in quiescence search
             continue;
         }
+        if depth_0 == 0 && see::see(g, el.si, el.di) < 0 {
+            // and captures which lose material
+            continue;
+        }
+        if exclude_root && g.root_exclude.contains(&(el.si, el.di, el.promote_to)) {
+            continue;
+        }
         if cup == 0 {
             if (eval_cnt > 0 && g.start_time.elapsed() > g.time_3)
                 || (eval_cnt > 1 && g.start_time.elapsed() > g.time_2)
             {
-                println!(
-                    "time break, eval count: {} {} {}",
-                    eval_cnt, hash_res_kks_high, el.eval_depth
-                );
+                if g.verbose {
+                    println!(
+                        "time break, eval count: {} {} {}",
+                        eval_cnt, hash_res_kks_high, el.eval_depth
+                    );
+                }
                 //debug_assert!(eval_cnt as usize + 1 == hash_res_kks_high); // no, not always
                 if false && cfg!(feature = "salewskiChessDebug") {
                     println!("{:?}", hash_res.kks);
@@ -1840,7 +2159,7 @@ fn abeta(
         if el.eval_depth >= depth_0 as i8 {
             // this move was already evaluated, but was not good enough, no beta cutoff
             valid_move_found = true; // list contains only valid moves, as we delete or skip the invalid ones
-            debug_inc(&mut g.re_eval_skip);
+            g.stats.re_eval_skip += 1;
             m.score = pmq(el.s as i64, -cup);
             debug_assert!(m.score < beta);
         } else {
@@ -1849,15 +2168,22 @@ fn abeta(
             let is_a_pawnelsf = is_a_pawn(el.sf);
             let is_a_kingelsf = is_a_king(el.sf);
             let elsieldi = el.si - el.di;
-            let little_castling = is_a_kingelsf && elsieldi == 2; // castling candidates
-            let big_castling = is_a_kingelsf && elsieldi == -2;
+            let castling = is_a_kingelsf && chess960::is_castling(g, el.si, el.di); // castling candidate
+            let castle_moves = if castling {
+                chess960::castling_moves_unchecked(g, el.si, el.di)
+            } else {
+                Default::default()
+            };
             let en_passant = is_a_pawnelsf && el.df == VOID_ID as i8 && odd(elsieldi); // move is an eP capture candidate
-            if little_castling && (g.has_moved.contains(el.si) || g.has_moved.contains(el.si - 3)) {
-                // we always generate castling moves but
+            if castling && (g.has_moved.contains(el.si) || g.has_moved.contains(castle_moves[1].0))
+            {
+                // we always generate castling moves but skip them when not allowed.
                 continue;
             }
-            if big_castling && (g.has_moved.contains(el.si) || g.has_moved.contains(el.si + 4)) {
-                // skip them when not allowed.
+            if castling && chess960::king_path(castle_moves).any(|p| in_check(g, p, color, true)) {
+                // the king may not pass an attacked square. We test before the move, as in Chess960
+                // the castled rook may block the attacker.
+                el.s = IGNORE_MARKER_LOW_INT16; // mark for deletion or ignore
                 continue;
             }
             if en_passant && el.di != ep_pos {
@@ -1867,7 +2193,7 @@ fn abeta(
             // does such extents make any sense? We can do it, but we have to be careful and test.
             // we could additional scale the extent, e.g. by dividing by (cup+1) to apply early only.
             v_depth_inc = 0; // default
-            if !NO_EXTEND_AT_ALL && depth_0 > 0 && !g.is_endgame {
+            if g.params.extend.enabled && depth_0 > 0 && !g.mop_up {
                 // EXTEND tests are not very cheap, so do then only in higher levels
                 // the following code is ordered so that v_depth_inc never is decreased, avoiding max() or lift() calls.
                 if false && SELECT_EXTEND {
@@ -1879,23 +2205,23 @@ fn abeta(
                     // we use in_check() test for king and queen instead!
                     v_depth_inc = 1; // not 2, because sdi gives already +1
                 }
-                if CAPTURE_EXTEND || EQUAL_CAPTURE_EXTEND || LARGE_CAPTURE_EXTEND {
+                if CAPTURE_EXTEND || g.params.extend.equal_capture || LARGE_CAPTURE_EXTEND {
                     if el.df != VOID_ID as i8 {
                         if CAPTURE_EXTEND {
                             assert!(false); // covered by ddi array already
                             v_depth_inc = 2;
                         }
-                        if EQUAL_CAPTURE_EXTEND || LARGE_CAPTURE_EXTEND {
-                            let immediate_gain = FIGURE_VALUE[el.df.abs() as usize]
-                                - FIGURE_VALUE[el.sf.abs() as usize];
+                        if g.params.extend.equal_capture || LARGE_CAPTURE_EXTEND {
+                            // the result of the whole exchange, not only of this capture
+                            let exchange = see::see(g, el.si, el.di);
                             if LARGE_CAPTURE_EXTEND {
                                 assert!(false); // bad idea
-                                if immediate_gain.abs() > PAWN_VALUE {
+                                if exchange.abs() > g.params.pawn_value {
                                     v_depth_inc = 4;
                                 }
                             }
-                            if EQUAL_CAPTURE_EXTEND && depth_0 > 1 {
-                                if immediate_gain.abs() < 25 {
+                            if g.params.extend.equal_capture && depth_0 > 1 {
+                                if exchange.abs() < 25 {
                                     if true || g.move_chain[cup as usize] != el.di {
                                         // only when not a re-capture
                                         v_depth_inc = 4;
@@ -1905,7 +2231,7 @@ fn abeta(
                         }
                     }
                 }
-                if PAWN_MARCH_EXTEND {
+                if g.params.extend.pawn_march {
                     if is_a_pawnelsf && hash_res.pop_cnt < 32 - 6 {
                         let rows_to_go = rows_to_go(el.si, color);
                         if g.move_chain[cup as usize] == el.si {
@@ -1921,12 +2247,12 @@ fn abeta(
                         }
                     }
                 }
-                if CHECK_EXTEND && cup > 1 && depth_0 > 1 {
+                if g.params.extend.check && cup > 1 && depth_0 > 1 {
                     if hash_res.in_check {
                         v_depth_inc = 4 + (cup == 2) as i64 * 4;
                     }
                 }
-                if PROMOTE_EXTEND && el.promote_to.abs() != VOID_ID as i8 {
+                if g.params.extend.promote && el.promote_to.abs() != VOID_ID as i8 {
                     v_depth_inc = 4;
                 }
                 if RANGE_EXTEND {
@@ -1949,26 +2275,20 @@ fn abeta(
                 put_tte(g, encoded_board, hash_res, depth_0 as i64, hash_pos); // store this for a fast return next time
                 return result;
             }
-            g.board[el.si as usize] = VOID_ID; // the basic movement
-            g.board[el.di as usize] = el.sf as i64;
+            if castling {
+                chess960::castle(&mut g.board, castle_moves, false);
+            } else {
+                g.board[el.si as usize] = VOID_ID; // the basic movement
+                g.board[el.di as usize] = el.sf as i64;
+            }
             let hmback = g.has_moved.clone(); // backup
             g.has_moved.insert(el.si); // may be a king or rook move, so castling is forbidden in future
-            if little_castling {
-                // small rochade
-                if CASTLING_EXTEND {
+            if castling {
+                // rochade
+                if g.params.extend.castling {
                     v_depth_inc = 4;
                 }
-                g.board[el.di as usize + 1] = g.board[el.di as usize - 1];
-                g.board[el.di as usize - 1] = VOID_ID;
-                g.has_moved.insert(el.di - 1);
-            } else if big_castling {
-                // big rochade
-                if CASTLING_EXTEND {
-                    v_depth_inc = 4;
-                }
-                g.board[el.di as usize - 1] = g.board[el.di as usize + 2];
-                g.board[el.di as usize + 2] = VOID_ID;
-                g.has_moved.insert(el.di + 2);
+                g.has_moved.insert(castle_moves[1].0);
             } else if en_passant {
                 g.board[(el.di as i64 - color * 8) as usize] = VOID_ID;
             } else if is_a_pawnelsf && base_row(el.di) {
@@ -2004,35 +2324,85 @@ fn abeta(
             } else {
                 g.to_100 += 1;
             }
+            let new_v_depth =
+                v_depth + v_depth_inc + sdi[el.sf.abs() as usize] + ddi[el.df.abs() as usize];
+            let sw = &g.params.search;
+            // the first move gets the full window, all others are expected to fail low
+            let null_window = sw.pvs && valid_move_found && depth_0 > 0 && beta - alpha > 1;
+            let reduction = if sw.lmr
+                && depth_0 >= sw.lmr_min_depth as usize
+                && eval_cnt > sw.lmr_min_moves as i64
+                && v_depth_inc == 0
+                && el.df == VOID_ID as i8
+                && el.promote_to == 0
+                && !hash_res.in_check
+            {
+                V_RATIO
+            } else {
+                0
+            };
+            let window = if null_window { -alpha - 1 } else { -beta };
+            let check_given = variant::count_check(g, color);
             m = abeta(
                 g,
                 opp_color(color),
-                v_depth + v_depth_inc + sdi[el.sf.abs() as usize] + ddi[el.df.abs() as usize],
+                new_v_depth - reduction,
                 cup + 1,
-                -beta,
+                window,
                 -alpha,
                 hash_res_kks_len as i64,
                 nep_pos,
             );
+            if reduction > 0 && m.score != LOWEST_SCORE as i64 && -m.score > alpha {
+                // the reduced search failed high, so search again with full depth
+                m = abeta(
+                    g,
+                    opp_color(color),
+                    new_v_depth,
+                    cup + 1,
+                    window,
+                    -alpha,
+                    hash_res_kks_len as i64,
+                    nep_pos,
+                );
+            }
+            if null_window && m.score != LOWEST_SCORE as i64 && -m.score > alpha && -m.score < beta
+            {
+                // a new best move, we need its score
+                m = abeta(
+                    g,
+                    opp_color(color),
+                    new_v_depth,
+                    cup + 1,
+                    -beta,
+                    -alpha,
+                    hash_res_kks_len as i64,
+                    nep_pos,
+                );
+            }
+            if check_given {
+                variant::uncount_check(g, color);
+            }
 
             if m.score != LOWEST_SCORE as i64 {
                 // not a hard cut with invalid result
                 m.score *= -1;
                 if rep_test_needed {
                     // deal with repetive positions
-                    if m.score < 0 {
+                    let draw = draw_score(g, color);
+                    if m.score < draw {
                         // if we are in a weak position, we will request a draw if possible
                         // or does repetition always enforces a draw, as on chess.com?
                         if num_reps > 2 {
                             // this will be the third repetition, so draw can be requested
-                            m.score = 0; // draw
+                            m.score = draw;
                         }
                     }
                     *g.history.get_mut(&new_state).unwrap() -= 1; // pop() -- we might remove entry if zero
                 }
                 if g.to_100 == 100 {
                     // human would request a draw, but in computer chess it becomes typically a draw automatically
-                    m.score = 0; // draw
+                    m.score = draw_score(g, color);
                 }
                 if m.state == STATE_CAN_CAPTURE_KING {
                     el.s = IGNORE_MARKER_LOW_INT16; // mark for deletion
@@ -2049,36 +2419,13 @@ fn abeta(
             }
             g.has_moved = hmback; // reset board state
             g.to_100 = to_100_bak;
-            g.board[el.di as usize] = el.df as i64;
-            g.board[el.si as usize] = el.sf as i64;
-            if en_passant {
-                g.board[(el.di as i64 - color * 8) as usize] = -el.sf as i64;
-            }
-            if little_castling {
-                // small rochade
-                g.board[el.di as usize - 1] = g.board[el.di as usize + 1];
-                g.board[el.di as usize + 1] = VOID_ID;
-                // g.has_moved.excl(el.di - 1) // use backup instead
-                let mut h: BitSet = Default::default();
-                h.insert(el.si);
-                h.insert(el.si - 1);
-                h.insert(el.di);
-                if !m.control.is_disjoint(&h) {
-                    el.s = IGNORE_MARKER_LOW_INT16; // mark for deletion or ignore
-                    continue; // was illegal, so ignore
-                }
-            } else if big_castling {
-                // big rochade
-                g.board[el.di as usize + 2] = g.board[el.di as usize - 1];
-                g.board[el.di as usize - 1] = VOID_ID;
-                // g.has_moved.excl(el.di + 2)
-                let mut h: BitSet = Default::default();
-                h.insert(el.si);
-                h.insert(el.si + 1);
-                h.insert(el.di);
-                if !m.control.is_disjoint(&h) {
-                    el.s = IGNORE_MARKER_LOW_INT16;
-                    continue; // was illegal, so ignore
+            if castling {
+                chess960::castle(&mut g.board, castle_moves, true);
+            } else {
+                g.board[el.di as usize] = el.df as i64;
+                g.board[el.si as usize] = el.sf as i64;
+                if en_passant {
+                    g.board[(el.di as i64 - color * 8) as usize] = -el.sf as i64;
                 }
             }
             if m.score == LOWEST_SCORE as i64 {
@@ -2087,13 +2434,18 @@ fn abeta(
                 return result;
             }
             if m.score >= beta {
+                g.stats.beta_cut += 1;
+                result.src = el.si as i64; // for a fail high at the root, see reply()
+                result.dst = el.di as i64;
+                result.promote_to = el.promote_to as i64;
+                ordering::update(g, color, cup, depth_0, el);
                 // debug_assert!(is_sorted2(hash_res.kks, hash_res_kks_high + 1, hash_res.kks.high)) // no, can be more than one partition
                 ixsort(&mut hash_res.kks, hash_res_kks_high + 1);
                 //debug_assert!(is_sorted(&hash_res.kks, hash_res_kks_high as usize));
                 //debug_assert!(hash_res.floor[depth_0 as usize].s < m.score as i16); // always true, due to beta cutoff test at top of proc
                 hash_res.floor[depth_0].s = pmq(m.score, cup) as i16;
                 put_tte(g, encoded_board, hash_res, depth_0 as i64, hash_pos);
-                result.score = beta;
+                result.score = m.score;
                 return result;
             }
         }
@@ -2111,7 +2463,7 @@ fn abeta(
             result.state = STATE_CHECKMATE;
             result.score = -KING_VALUE as i64 + cup as i64 - 1;
         } else {
-            result.score = 0;
+            result.score = draw_score(g, color);
             result.state = STATE_STALEMATE;
         }
     } else {
@@ -2120,7 +2472,9 @@ fn abeta(
     //debug_assert!(hash_res.score[depth_0].s == INVALID_SCORE);
     //debug_assert!(hash_res.kks_high == hash_res.kks.high) // not always, due to cut_time break for cup == 0
     ixsort(&mut hash_res.kks, hash_res_kks_high); //}
-    if result.score > alpha_0 && !time_break
+    if exclude_root {
+        // not the score of this position, so we store only the move list
+    } else if result.score > alpha_0 && !time_break
         || result.state == STATE_CHECKMATE
         || result.state == STATE_STALEMATE
     {
@@ -2134,8 +2488,9 @@ fn abeta(
         hash_res.score[depth_0].s = pmq(result.score, cup) as i16;
         hash_res.score[depth_0].si = result.src as i8;
         hash_res.score[depth_0].di = result.dst as i8;
-    } else {
-        // if time_break {
+        hash_res.score[depth_0].promote_to = result.promote_to as i8;
+    } else if time_break && result.score > alpha_0 {
+        // the best score of the moves searched so far; a fail low is no floor, but an upper bound
         lift_i16(
             &mut hash_res.floor[depth_0].s,
             pmq(result.score, cup) as i16,
@@ -2175,21 +2530,15 @@ fn _check_mate_in(score: i64) -> i64 {
     }
 }
 
-fn alphabeta(g: &mut Game, color: Color, depth: i64, ep_pos: i8) -> Move {
-    debug_assert!((0.1..10.0).contains(&g.secs_per_move));
-    //g.time_0 = Duration::from_secs_f32(g.secs_per_move * 0.7);
-    g.time_2 = Duration::from_secs_f32(g.secs_per_move * 1.5);
-    g.time_3 = Duration::from_secs_f32(g.secs_per_move * 2.5);
-    //g.time_4 = Duration::from_secs_f32(g.secs_per_move * 5.0);
+fn alphabeta(g: &mut Game, color: Color, depth: i64, ep_pos: i8, alpha: i64, beta: i64) -> Move {
     g.start_time = Instant::now();
-    reset_statistics(g);
     let result = abeta(
         g,
         color,
         depth * V_RATIO + V_RATIO / 2,
         0,
-        -AB_INF as i64,
-        AB_INF as i64,
+        alpha,
+        beta,
         20,
         ep_pos,
     );
@@ -2208,11 +2557,58 @@ const FLAG_CAPTURE: i32 = 1;
 const FLAG_EP: i32 = 2;
 const FLAG_PROMOTION: i32 = 3;
 const FLAG_PROCAP: i32 = 4;
+pub const FLAG_CASTLING: i32 = 5; // the GUI moves the rook as well, see castling_moves()
+
+// do the move from p0 to p1 after testing that it is legal, pawns are promoted to a queen.
+// The result is one of the FLAG constants, for move_to_str().
+pub fn do_move(g: &mut Game, p0: Position, p1: Position) -> Result<i32, EngineError> {
+    check_squares(p0 as i64, p1 as i64)?;
+    if !move_is_valid2(g, p0 as i64, p1 as i64) {
+        let m = Move {
+            src: p0 as i64,
+            dst: p1 as i64,
+            ..Default::default()
+        };
+        return Err(EngineError::IllegalMove(move_to_uci(&m)));
+    }
+    let flag = do_move_unchecked(g, p0, p1, false);
+    record_move(g, p0, p1, flag);
+    Ok(flag)
+}
 
-pub fn do_move(g: &mut Game, p0: Position, p1: Position, silent: bool) -> i32 {
+fn record_move(g: &mut Game, p0: Position, p1: Position, flag: i32) {
+    let promote_to = if flag == FLAG_PROMOTION || flag == FLAG_PROCAP {
+        g.board[p1 as usize]
+    } else {
+        VOID_ID
+    };
+    g.played.push(Move {
+        src: p0 as i64,
+        dst: p1 as i64,
+        promote_to,
+        ..Default::default()
+    });
+    let next = -(g.move_counter as Color % 2) * 2 + 1;
+    variant::after_move(g, next);
+}
+
+fn check_squares(p0: i64, p1: i64) -> Result<(), EngineError> {
+    for p in [p0, p1] {
+        if !(0..64).contains(&p) {
+            return Err(EngineError::InvalidSquare(p));
+        }
+    }
+    Ok(())
+}
+
+// the caller ensures a legal move, silent moves are for move generation and are not recorded
+fn do_move_unchecked(g: &mut Game, p0: Position, p1: Position, silent: bool) -> i32 {
     p(g.board);
     let mut result: i32 = 0;
-    if !is_void_at(&g, p1) {
+    let castling = chess960::is_castling(g, p0, p1);
+    if castling {
+        result = FLAG_CASTLING;
+    } else if !is_void_at(&g, p1) {
         result = FLAG_CAPTURE;
     }
     if !silent {
@@ -2221,21 +2617,15 @@ pub fn do_move(g: &mut Game, p0: Position, p1: Position, silent: bool) -> i32 {
         if is_a_pawn_at(&g, p0) && (p0 - p1).abs() == 16 {
             g.pjm = (p0 + p1) / 2;
         }
-        if is_a_pawn_at(&g, p0) || !is_void_at(&g, p1) {
-            // test for castlings as well?
+        if is_a_pawn_at(&g, p0) || result == FLAG_CAPTURE {
             g.to_100 = 0;
         } else {
             g.to_100 += 1;
         }
     }
-    if (p1 - p0).abs() == 2 && is_a_king_at(&g, p0) {
-        if col(p1) == 1 {
-            g.board[p0 as usize - 1] = g.board[p0 as usize - 3];
-            g.board[p0 as usize - 3] = VOID_ID;
-        } else {
-            g.board[p0 as usize + 1] = g.board[p0 as usize + 4];
-            g.board[p0 as usize + 4] = VOID_ID;
-        }
+    if castling {
+        let moves = chess960::castling_moves_unchecked(g, p0, p1);
+        chess960::castle(&mut g.board, moves, false);
     } else if base_row(p1) && is_a_pawn_at(&g, p0) {
         g.board[p0 as usize] *= QUEEN_ID;
         result = if result == FLAG_CAPTURE {
@@ -2247,20 +2637,23 @@ pub fn do_move(g: &mut Game, p0: Position, p1: Position, silent: bool) -> i32 {
         result = FLAG_EP;
         g.board[(p1 as i64 - g.board[p0 as usize] * 8) as usize] = VOID_ID;
     }
-    g.board[p1 as usize] = g.board[p0 as usize];
-    g.board[p0 as usize] = VOID_ID;
+    if !castling {
+        g.board[p1 as usize] = g.board[p0 as usize];
+        g.board[p0 as usize] = VOID_ID;
+    }
     if !silent {
         if is_a_pawn_at(&g, p1) || result != FLAG_PLAIN {
             g.history.clear();
         } else {
-            let new_state = encode_board(&g, signum(g.board[p1 as usize]) as Color);
+            let new_state = encode_board(&g, -signum(g.board[p1 as usize]) as Color); // as in abeta(), the side to move next
             *g.history.entry(new_state).or_insert(0) += 1;
         }
     }
     //when defined(salewskiChessDebug):
     if true {
         if !silent {
-            g.debug_list.push(move_to_str(&g, p0, p1, result));
+            g.debug_list
+                .push(move_to_str(&g, p0, p1, result).unwrap_or_default());
             //println!("--");
         }
     }
@@ -2269,7 +2662,7 @@ pub fn do_move(g: &mut Game, p0: Position, p1: Position, silent: bool) -> i32 {
     result
 }
 
-pub fn tag(g: &mut Game, si: i64) -> KKS {
+fn tag(g: &mut Game, si: i64) -> KKS {
     let mut kk: KK = Default::default();
     kk.sf = g.board[si as usize] as i8;
     let color = signum(kk.sf as i64) as Color;
@@ -2288,40 +2681,23 @@ pub fn tag(g: &mut Game, si: i64) -> KKS {
         KING_ID => walk_king(&g, kk, &mut s),
         _ => {}
     }
-    if si == 3 || si == 3 + 7 * 8 {
-        const // king, void, void, void, rook, kingDelta+2
-      Q: [[usize; 6]; 2] = [[3, 2, 1, 1, 0, 0], [3, 4, 5, 6, 7, 4]];
-        let k = W_KING * color;
-        let r = W_ROOK * color;
-        for i in 0..(1 + 1) {
-            // castlings both sides
-            let mut q = Q[i];
-            if color == COLOR_BLACK {
-                for j in 0..(4 + 1) {
-                    q[j as usize] += 7 * 8;
-                }
-            }
-            if g.board[q[0]] == k
-                && g.board[q[1]] == 0
-                && g.board[q[2]] == 0
-                && g.board[q[3]] == 0
-                && g.board[q[4]] == r
-                && !g.has_moved.contains(q[0])
-                && !g.has_moved.contains(q[4])
+    if kk.sf.abs() as i64 == KING_ID {
+        // castlings both sides
+        let mut castlings: Vec<KK> = Vec::new();
+        chess960::walk_castlings(g, color, kk, &mut castlings);
+        for el in castlings {
+            let moves = chess960::castling_moves_unchecked(g, el.si, el.di);
+            if !g.has_moved.contains(el.si)
+                && !g.has_moved.contains(moves[1].0)
+                && !chess960::king_path(moves).any(|p| in_check(g, p, color, true))
             {
-                if !(in_check(&g, q[0] as i8, color, true)
-                    || in_check(&g, q[1] as i8, color, true)
-                    || in_check(&g, q[2] as i8, color, true))
-                {
-                    kk.di = (q[0] + q[5] - 2) as i8;
-                    s.push(kk);
-                }
+                s.push(el);
             }
         }
     }
     let backup = g.board;
     for el in &mut s {
-        do_move(g, si as i8, el.di, true);
+        do_move_unchecked(g, si as i8, el.di, true);
         if in_check(&g, king_pos(&g, color), color, true) {
             el.s = 0
         }
@@ -2332,6 +2708,9 @@ pub fn tag(g: &mut Game, si: i64) -> KKS {
 }
 
 pub fn move_is_valid2(g: &mut Game, si: i64, di: i64) -> bool {
+    if check_squares(si, di).is_err() {
+        return false;
+    }
     let next = -(g.move_counter as Color % 2) * 2 + 1;
     signum(g.board[si as usize]) as Color == next && tag(g, si).iter().any(|&it| it.di == di as i8)
 }
@@ -2339,11 +2718,11 @@ pub fn move_is_valid2(g: &mut Game, si: i64, di: i64) -> bool {
 const FIG_STR: [&str; 7] = ["  ", "  ", "N_", "B_", "R_", "Q_", "K_"];
 
 fn col_str(c: Col) -> char {
-    char::from_u32('H' as u32 - c as u32).unwrap()
+    b"HGFEDCBA"[c as usize & 7] as char
 }
 
 fn row_str(c: Col) -> char {
-    char::from_u32('1' as u32 + c as u32).unwrap()
+    b"12345678"[c as usize & 7] as char
 }
 
 pub fn get_board(g: &Game) -> Board {
@@ -2351,12 +2730,19 @@ pub fn get_board(g: &Game) -> Board {
 }
 
 // call this after do_move()
-pub fn move_to_str(g: &Game, si: Position, di: Position, flag: i32) -> String {
+pub fn move_to_str(g: &Game, si: Position, di: Position, flag: i32) -> Result<String, EngineError> {
+    check_squares(si as i64, di as i64)?;
     //when true: // move_is_valid(si, di): // avoid unnecessary expensive test
     let mut result: String;
+    // the moved piece, for a castling the king
+    let to = if flag == FLAG_CASTLING {
+        chess960::castling_moves_unchecked(g, si, di)[0].1
+    } else {
+        di
+    };
     if true {
-        if g.board[di as usize].abs() == KING_ID && (di - si).abs() == 2 {
-            result = String::from(if col(di) == 1 { "o-o" } else { "o-o-o" });
+        if flag == FLAG_CASTLING {
+            result = String::from(if di < si { "o-o" } else { "o-o-o" });
         } else {
             result = String::from(FIG_STR[g.board[di as usize].abs() as usize]);
             result.push(col_str(col(si)));
@@ -2374,8 +2760,8 @@ pub fn move_to_str(g: &Game, si: Position, di: Position, flag: i32) -> String {
         }
         if in_check(
             &g,
-            king_pos(&g, (-signum(g.board[di as usize])) as Color),
-            (-signum(g.board[di as usize])) as Color,
+            king_pos(&g, (-signum(g.board[to as usize])) as Color),
+            (-signum(g.board[to as usize])) as Color,
             true,
         ) {
             result.push_str(" +");
@@ -2383,10 +2769,10 @@ pub fn move_to_str(g: &Game, si: Position, di: Position, flag: i32) -> String {
     } else {
         result = String::from("invalid move");
     }
-    result
+    Ok(result)
 }
 
-pub fn _m_2_str(g: &Game, si: Position, di: Position) -> String {
+fn _m_2_str(g: &Game, si: Position, di: Position) -> String {
     let mut result: String;
     let mut flag: i32 = 0;
     if !is_void_at(&g, di) {
@@ -2403,8 +2789,8 @@ pub fn _m_2_str(g: &Game, si: Position, di: Position) -> String {
     }
     if true {
         // move_is_valid(si, di): // avoid unnecessary expensive test
-        if g.board[di as usize].abs() == KING_ID && (di - si).abs() == 2 {
-            result = String::from(if col(di) == 1 { "o-o" } else { "o-o-o" });
+        if chess960::is_castling(g, si, di) {
+            result = String::from(if di < si { "o-o" } else { "o-o-o" });
         } else {
             result = String::from(FIG_STR[g.board[si as usize].abs() as usize]);
             result.push(col_str(col(si)));
@@ -2429,8 +2815,12 @@ pub fn _m_2_str(g: &Game, si: Position, di: Position) -> String {
     result
 }
 
-// Endgame = no pawns, weaker side has no queen, no rook and not two bishops.
-fn setup_endgame(g: &mut Game) -> bool {
+// Mop-up endgame = no pawns, weaker side has no queen, no rook and not two bishops.
+// Then the endgame tables hunt the weaker king, else the default tables are restored.
+fn setup_mop_up(g: &mut Game) -> bool {
+    if g.mop_up {
+        init_freedom(g);
+    }
     let mut p: [i64; 13] = [0; 13];
     let mut h: [i64; 3] = [0; 3]; //array[-1..1, i64] // total number of pieces
     let mut b: [i64; 3] = [0; 3]; //array[-1..1, i64] // single bishop position
@@ -2449,7 +2839,7 @@ fn setup_endgame(g: &mut Game) -> bool {
     }
     for i in (B_KING + ARRAY_BASE_6) as usize..(W_KING + ARRAY_BASE_6 + 1) as usize {
         for j in POS_RANGE_US {
-            g.freedom[i][j] = 0
+            g.freedom_eg[i][j] = 0
         }
     }
     for s in [-1, 1] {
@@ -2470,16 +2860,16 @@ fn setup_endgame(g: &mut Game) -> bool {
                 // chase to selected corner
                 if odd(col(b[(s + 1) as usize] as i8) as i8) != odd(row(b[(s + 1) as usize] as i8))
                 {
-                    g.freedom[opp_king as usize][i as usize] =
+                    g.freedom_eg[opp_king as usize][i as usize] =
                         -sqr(row(i) as i64 - col(i) as i64) as i16; // sqr may be better than abs when both sites are
                 } else {
                     // struggling, i.e. K + B + B vs K + B
-                    g.freedom[opp_king as usize][i as usize] =
+                    g.freedom_eg[opp_king as usize][i as usize] =
                         -sqr(row(i) as i64 + col(i) as i64 - 7) as i16;
                 }
             } else {
                 // chase to border and/or arbitrary corner
-                g.freedom[opp_king as usize][i as usize] =
+                g.freedom_eg[opp_king as usize][i as usize] =
                     -sqr((2 * row(i) - 7).abs() as i64 + (2 * col(i) - 7).abs() as i64 / 2) as i16;
             }
         }
@@ -2487,14 +2877,116 @@ fn setup_endgame(g: &mut Game) -> bool {
     return true;
 }
 
-pub fn reply(g: &mut Game) -> Move {
+const MOVE_OVERHEAD: f32 = 0.05; // seconds we keep in reserve for the GUI and the move transfer
+const EXPECTED_GAME_LENGTH: i64 = 45; // for sudden death, we assume the game lasts about that many moves
+const MIN_MOVES_TO_GO: i64 = 20;
+const UNSTABLE_SCORE_DROP: i64 = 30; // a larger score drop between iterations indicates an unstable best move
+const ASPIRATION_MIN_DEPTH: usize = 5; // the iterations before search with the full window
+
+// the nominal time for the current move, and an upper limit which we never exceed, in seconds.
+// Without a clock we use secs_per_move, with the old multipliers for the deadlines.
+fn allocate_time(g: &Game) -> (f32, f32) {
+    match g.clock {
+        None => {
+            debug_assert!((0.1..10.0).contains(&g.secs_per_move));
+            (g.secs_per_move, g.secs_per_move * 5.0)
+        }
+        Some(c) => {
+            let side = (g.move_counter % 2) as usize;
+            let left = (c.remaining[side] - MOVE_OVERHEAD).max(0.01);
+            let moves_to_go = if c.moves_to_go > 0 {
+                c.moves_to_go as i64
+            } else {
+                max(
+                    EXPECTED_GAME_LENGTH - g.move_counter as i64 / 2,
+                    MIN_MOVES_TO_GO,
+                )
+            };
+            let hard = if moves_to_go == 1 {
+                left * 0.8
+            } else {
+                left * 0.4
+            };
+            let nominal = left / moves_to_go as f32 + c.increment[side] * 0.8;
+            (nominal.min(hard).max(0.01), hard.max(0.01))
+        }
+    }
+}
+
+// the deadlines for the search, see reply() and abeta()
+fn set_deadlines(g: &mut Game, nominal: f32, hard: f32) {
+    g.time_0 = Duration::from_secs_f32((nominal * 0.7).min(hard)); // start no new iteration after this
+    g.time_2 = Duration::from_secs_f32((nominal * 1.5).min(hard)); // stop evaluating root moves
+    g.time_3 = Duration::from_secs_f32((nominal * 2.5).min(hard)); // even when only one is evaluated
+    g.time_4 = Duration::from_secs_f32(hard); // hard abort of the iteration
+}
+
+// the best move is unstable, so we spend more time, but at most twice the nominal time
+fn extend_deadlines(g: &mut Game, extension: &mut f32, nominal: f32, hard: f32) {
+    *extension = (*extension * 1.5).min(2.0);
+    set_deadlines(g, nominal * *extension, hard);
+}
+
+fn clear_deadlines(g: &mut Game) {
+    g.time_0 = Duration::MAX;
+    g.time_2 = Duration::MAX;
+    g.time_3 = Duration::MAX;
+    g.time_4 = Duration::MAX;
+}
+
+pub fn reply(g: &mut Game, limits: &SearchLimits) -> Result<SearchResult, EngineError> {
+    let best = search_move(g, limits)?;
+    Ok(SearchResult {
+        best,
+        stats: g.stats,
+    })
+}
+
+fn search_move(g: &mut Game, limits: &SearchLimits) -> Result<Move, EngineError> {
+    search_move_since(g, limits, Instant::now())
+}
+
+// the time limits count from start_time, so that the lines of reply_multi_pv() share them
+fn search_move_since(
+    g: &mut Game,
+    limits: &SearchLimits,
+    start_time: Instant,
+) -> Result<Move, EngineError> {
     //let back_move
+    reset_statistics(g);
+    let next = -(g.move_counter as Color % 2) * 2 + 1;
+    if legal_moves(g).is_empty() || variant::lost(g, next) {
+        return Err(EngineError::NoLegalMove);
+    }
     let mut move_result = Move {
         state: STATE_NO_VALID_MOVE,
         score: LOWEST_SCORE as i64,
         ..Default::default()
     };
+    if limits.mate == 0 {
+        if let Some(m) = book::book_move(g) {
+            if g.verbose {
+                println!("book move");
+            }
+            return Ok(m);
+        }
+        if g.skill_level < MAX_SKILL_LEVEL && g.root_exclude.is_empty() {
+            return skill::weakened_reply(g, limits);
+        }
+        if g.root_exclude.is_empty() {
+            if let Some(m) = syzygy::root_move(g) {
+                return Ok(m);
+            }
+        }
+    }
     let color = ((g.move_counter as i64 + 1) % 2) * 2 - 1;
+    if color != g.root_color && g.contempt != [0; 2] {
+        // the stored draw scores are for the other side
+        for el in &mut g.tt {
+            *el = Default::default();
+        }
+    }
+    g.root_color = color;
     let mut result: Move = Default::default();
     //println!("{:?}", g.freedom);
     if cfg!(feature = "salewskiChessDebug") {
@@ -2504,11 +2996,34 @@ pub fn reply(g: &mut Game) -> Move {
         }
     }
     let mut depth = 0;
-    let start_time = Instant::now();
-    g.time_0 = Duration::from_secs_f32(g.secs_per_move * 0.7);
-    if setup_endgame(g) {
+    let mut max_depth = if limits.max_depth > 0 {
+        min(limits.max_depth, MAX_DEPTH)
+    } else {
+        MAX_DEPTH
+    };
+    if limits.mate > 0 {
+        // the mate is only seen when the opponent has no legal reply, so one ply more
+        max_depth = min(max_depth, 2 * limits.mate);
+    }
+    let (nominal, hard) = if limits.movetime > 0.0 {
+        (limits.movetime, limits.movetime)
+    } else {
+        allocate_time(g)
+    };
+    if limits.timed() {
+        set_deadlines(g, nominal, hard);
+    } else {
+        clear_deadlines(g);
+        if limits.movetime > 0.0 {
+            g.time_4 = Duration::from_secs_f32(hard);
+        }
+    }
+    let mut extension: f32 = 1.0;
+    g.node_limit = u64::MAX; // like time_4, for the first iteration
+    ordering::new_search(g);
+    g.mop_up = setup_mop_up(g);
+    if g.mop_up && g.verbose {
         println!("endgame");
-        g.is_endgame = true;
     }
     for i in 0..13 {
         pf(g.freedom[i]);
@@ -2516,34 +3031,630 @@ pub fn reply(g: &mut Game) -> Move {
     for el in &mut g.tt {
         el.res.pri = i64::MIN
     }
-    println!("--");
-    g.time_4 = Duration::MAX;
-    while depth < MAX_DEPTH {
+    if g.verbose {
+        println!("--");
+    }
+    g.time_4 = Duration::MAX; // the first iteration must complete
+    g.can_stop = false;
+    while depth < max_depth {
         depth += 1;
-        result = alphabeta(g, color as i64, depth as i64, g.pjm);
+        // an aspiration window around the previous score, widened when the search fails low or high
+        let mut delta = g.params.search.aspiration_window as i64;
+        let (mut alpha, mut beta) = if delta > 0
+            && depth >= ASPIRATION_MIN_DEPTH
+            && move_result.score.abs() < KING_VALUE_DIV_2 as i64
+        {
+            (move_result.score - delta, move_result.score + delta)
+        } else {
+            (-AB_INF as i64, AB_INF as i64)
+        };
+        let mut fail_low = false;
+        loop {
+            result = alphabeta(g, color as i64, depth as i64, g.pjm, alpha, beta);
+            if result.score == LOWEST_SCORE as i64 || (result.score > alpha && result.score < beta)
+            {
+                break;
+            }
+            if result.score <= alpha {
+                fail_low = true;
+                if limits.timed() {
+                    // the best move got worse, so we need the re-search
+                    extend_deadlines(g, &mut extension, nominal, hard);
+                }
+            }
+            if start_time.elapsed() > g.time_0 {
+                // no time for the re-search. A fail high has a better move, a fail low only a bound.
+                if result.score <= alpha {
+                    // the move of the previous iteration is still our best guess
+                    return Ok(move_result);
+                }
+                break;
+            }
+            // a mate score is far outside of any window, so that side is opened at once
+            if result.score <= alpha {
+                alpha = if result.score <= -(KING_VALUE_DIV_2 as i64) {
+                    -AB_INF as i64
+                } else {
+                    max(alpha - delta, -AB_INF as i64)
+                };
+            } else {
+                beta = if result.score >= KING_VALUE_DIV_2 as i64 {
+                    AB_INF as i64
+                } else {
+                    min(beta + delta, AB_INF as i64)
+                };
+            }
+            delta *= 2;
+            g.stats.re_search += 1;
+            if g.verbose {
+                println!("aspiration re-search {} {}", alpha, beta);
+            }
+            if limits.timed() || limits.movetime > 0.0 {
+                g.time_4 = Duration::from_secs_f32(hard).saturating_sub(start_time.elapsed());
+            }
+        }
         if result.score != LOWEST_SCORE as i64 {
+            if limits.timed()
+                && depth > 4
+                && !fail_low
+                && (result.src != move_result.src
+                    || result.dst != move_result.dst
+                    || result.score < move_result.score - UNSTABLE_SCORE_DROP)
+            {
+                extend_deadlines(g, &mut extension, nominal, hard);
+            }
             move_result = result;
-            g.time_4 = Duration::from_secs_f32(g.secs_per_move * 5.0);
+            if limits.timed() || limits.movetime > 0.0 {
+                // abeta() measures time per iteration, but the hard limit is for the whole move
+                g.time_4 = Duration::from_secs_f32(hard).saturating_sub(start_time.elapsed());
+            }
+            if limits.max_nodes > 0 {
+                g.node_limit = limits.max_nodes;
+            }
+            g.can_stop = true;
         } else {
-            assert!(move_result.score != LOWEST_SCORE as i64);
-            println!("--- hard cut");
-            return move_result;
-        }
-        println!(
-            "Depth: {} {} score {} ({:.2} s)",
-            depth,
-            _m_2_str(g, result.src as i8, result.dst as i8),
-            result.score,
-            start_time.elapsed().as_millis() as f64 * 1e-3
-        );
-        if result.score.abs() > SURE_CHECKMATE as i64 {
+            debug_assert!(move_result.score != LOWEST_SCORE as i64); // the first iteration is never stopped
+            if g.verbose {
+                println!("--- hard cut");
+            }
+            return Ok(move_result);
+        }
+        if g.verbose {
+            println!(
+                "Depth: {} {} score {} ({:.2} s)",
+                depth,
+                _m_2_str(g, result.src as i8, result.dst as i8),
+                result.score,
+                start_time.elapsed().as_millis() as f64 * 1e-3
+            );
+        }
+        if let Some(mut progress) = g.progress.take() {
+            let info = SearchInfo {
+                depth,
+                // as sorted by reply_multi_pv(), after the earlier lines with the same score
+                multi_pv: 1 + g.root_scores.iter().filter(|&&s| s >= result.score).count(),
+                score: result.score,
+                nodes: g.stats.nodes,
+                stats: g.stats,
+                time: start_time.elapsed(),
+                pv: pv_from_tt(g, color, result, depth),
+            };
+            progress(&info);
+            g.progress = Some(progress);
+        }
+        if result.score.abs() > SURE_CHECKMATE as i64 || g.stop.load(Ordering::Relaxed) {
             break;
         }
         if start_time.elapsed() > g.time_0 {
             break;
         }
     }
-    return result;
+    Ok(result)
+}
+
+// follow the best moves stored in the transposition table, starting with the move first
+fn pv_from_tt(g: &mut Game, color: Color, first: Move, max_len: usize) -> Vec<Move> {
+    let backup = g.board;
+    let mut pv = vec![first];
+    let mut color = color;
+    do_move_unchecked(g, first.src as i8, first.dst as i8, true);
+    if first.promote_to != VOID_ID {
+        g.board[first.dst as usize] = first.promote_to;
+    }
+    while pv.len() < max_len {
+        color = opp_color(color);
+        let hash_pos = get_tte(g, encode_board(g, color));
+        if hash_pos < 0 {
+            break;
+        }
+        let hr = &g.tt[hash_pos as usize].res;
+        let best = match (0..(MAX_DEPTH + 1))
+            .rev()
+            .map(|i| hr.score[i])
+            .find(|el| el.s != INVALID_SCORE && el.si != el.di)
+        {
+            Some(el) => el,
+            None => break,
+        };
+        if signum(g.board[best.si as usize]) != color
+            || !tag(g, best.si as i64).iter().any(|&it| it.di == best.di)
+        {
+            break; // should not occur, but the table content is only a hint
+        }
+        pv.push(Move {
+            src: best.si as i64,
+            dst: best.di as i64,
+            score: best.s as i64,
+            promote_to: best.promote_to as i64,
+            ..Default::default()
+        });
+        do_move_unchecked(g, best.si, best.di, true);
+        if best.promote_to != VOID_ID as i8 {
+            g.board[best.di as usize] = best.promote_to as i64;
+        }
+    }
+    g.board = backup;
+    pv
+}
+
+// all legal moves of the side to move
+fn legal_moves(g: &mut Game) -> KKS {
+    let color = -(g.move_counter as Color % 2) * 2 + 1;
+    legal_moves_of(g, color)
+}
+
+// all legal moves of color, with castling and en passant as allowed by has_moved and pjm
+fn legal_moves_of(g: &mut Game, color: Color) -> KKS {
+    let mut s: KKS = Vec::new();
+    for si in POS_RANGE_US {
+        if signum(g.board[si]) == color {
+            s.append(&mut tag(g, si as i64));
+        }
+    }
+    s
+}
+
+// the best n moves with their scores and principal variations, best first.
+// Each line is a complete search with the given limits, skipping the root moves of the previous lines.
+// The time limits are for all lines together.
+pub fn reply_multi_pv(g: &mut Game, limits: &SearchLimits, n: usize) -> Vec<PvLine> {
+    let mut lines: Vec<PvLine> = Vec::new();
+    let n = min(n, legal_moves(g).len());
+    let color = ((g.move_counter as i64 + 1) % 2) * 2 - 1;
+    g.root_exclude.clear();
+    g.root_scores.clear();
+    let book = g.book.take(); // we want the searched scores, and more than one move
+    let skill_level = std::mem::replace(&mut g.skill_level, MAX_SKILL_LEVEL);
+    let mut stats = SearchStats::default();
+    let start_time = Instant::now(); // one time budget for all lines
+    while lines.len() < n && !(g.can_stop && g.stop.load(Ordering::Relaxed)) {
+        let m = search_move_since(g, limits, start_time);
+        stats += g.stats;
+        let m = match m {
+            Ok(m) if m.score != LOWEST_SCORE as i64 => m,
+            _ => break,
+        };
+        let pv = pv_from_tt(g, color, m, MAX_DEPTH);
+        lines.push(PvLine { score: m.score, pv });
+        g.root_exclude
+            .push((m.src as i8, m.dst as i8, m.promote_to as i8));
+        g.root_scores.push(m.score);
+    }
+    g.root_exclude.clear();
+    g.root_scores.clear();
+    g.book = book;
+    g.skill_level = skill_level;
+    g.stats = stats; // of all lines
+
+    // a later search may find a better score than an earlier one, as the TT content differs
+    lines.sort_by_key(|l| std::cmp::Reverse(l.score));
+    lines
+}
+
+// moves in UCI coordinate notation, e.g. e2e4 or e7e8q
+pub fn move_to_uci(m: &Move) -> String {
+    let mut result = String::new();
+    for p in [m.src as i8, m.dst as i8] {
+        result.push(col_str(col(p)).to_ascii_lowercase());
+        result.push(row_str(row(p)));
+    }
+    if m.promote_to.abs() == KNIGHT_ID {
+        result.push('n');
+    } else if m.promote_to.abs() == QUEEN_ID {
+        result.push('q');
+    }
+    result
+}
+
+fn square_from_str(s: &[u8]) -> Option<Position> {
+    if s.len() < 2 || !(b'a'..=b'h').contains(&s[0]) || !(b'1'..=b'8').contains(&s[1]) {
+        return None;
+    }
+    Some(7 - (s[0] - b'a') as i8 + (s[1] - b'1') as i8 * 8)
+}
+
+// parse a move in UCI notation and test that it is legal in the current position
+pub fn move_from_uci(g: &mut Game, s: &str) -> Result<Move, EngineError> {
+    let invalid = || EngineError::InvalidMove(s.to_string());
+    let b = s.as_bytes();
+    let si = square_from_str(b).ok_or_else(invalid)?;
+    let di = square_from_str(b.get(2..).ok_or_else(invalid)?).ok_or_else(invalid)?;
+    let promote_to = match b.get(4) {
+        None => 0,
+        Some(b'q') => QUEEN_ID,
+        Some(b'n') => KNIGHT_ID,
+        Some(_) => return Err(invalid()), // the engine promotes only to queen and knight
+    };
+    let kk = legal_moves(g)
+        .into_iter()
+        .find(|el| el.si == si && el.di == di && el.promote_to.abs() as i64 == promote_to)
+        .ok_or_else(|| EngineError::IllegalMove(s.to_string()))?;
+    Ok(Move {
+        src: si as i64,
+        dst: di as i64,
+        promote_to: kk.promote_to as i64,
+        ..Default::default()
+    })
+}
+
+// parse a move in standard algebraic notation, e.g. Nf3, exd5, O-O or e8=Q+, as used in EPD and PGN files.
+// The move must be legal and unambiguous in the current position.
+pub fn move_from_san(g: &mut Game, s: &str) -> Result<Move, EngineError> {
+    let invalid = || EngineError::InvalidMove(s.to_string());
+    let illegal = || EngineError::IllegalMove(s.to_string());
+    let san = s.trim_end_matches(['+', '#', '!', '?']);
+    let moves = legal_moves(g);
+    if san == "O-O" || san == "0-0" || san == "O-O-O" || san == "0-0-0" {
+        let kk = moves
+            .into_iter()
+            .find(|el| {
+                is_a_king(el.sf)
+                    && chess960::is_castling(g, el.si, el.di)
+                    && (el.si > el.di) == (san.len() == 3)
+            })
+            .ok_or_else(illegal)?;
+        return Ok(Move {
+            src: kk.si as i64,
+            dst: kk.di as i64,
+            ..Default::default()
+        });
+    }
+    let b = san.as_bytes();
+    let (piece, b) = match b.first().ok_or_else(invalid)? {
+        b'N' => (KNIGHT_ID, &b[1..]),
+        b'B' => (BISHOP_ID, &b[1..]),
+        b'R' => (ROOK_ID, &b[1..]),
+        b'Q' => (QUEEN_ID, &b[1..]),
+        b'K' => (KING_ID, &b[1..]),
+        _ => (PAWN_ID, b),
+    };
+    let (promote_to, b) = match b.last().ok_or_else(invalid)? {
+        b'Q' => (QUEEN_ID, &b[..b.len() - 1]),
+        b'N' => (KNIGHT_ID, &b[..b.len() - 1]),
+        b'R' | b'B' => return Err(invalid()), // the engine promotes only to queen and knight
+        _ => (VOID_ID, b),
+    };
+    let b = b.strip_suffix(b"=").unwrap_or(b);
+    if b.len() < 2 {
+        return Err(invalid());
+    }
+    let di = square_from_str(&b[b.len() - 2..]).ok_or_else(invalid)?;
+    let mut file = None; // disambiguation, or the source file of a pawn capture
+    let mut rank = None;
+    for &c in &b[..b.len() - 2] {
+        match c {
+            b'a'..=b'h' => file = Some(7 - (c - b'a') as i8),
+            b'1'..=b'8' => rank = Some((c - b'1') as i8),
+            b'x' => {}
+            _ => return Err(invalid()),
+        }
+    }
+    let mut found = moves.into_iter().filter(|el| {
+        el.sf.abs() as i64 == piece
+            && el.di == di
+            && !(piece == KING_ID && chess960::is_castling(g, el.si, el.di))
+            && el.promote_to.abs() as i64 == promote_to
+            && file.is_none_or(|f| col(el.si) == f)
+            && rank.is_none_or(|r| row(el.si) == r)
+    });
+    let kk = found.next().ok_or_else(illegal)?;
+    if found.next().is_some() {
+        return Err(invalid()); // ambiguous
+    }
+    Ok(Move {
+        src: kk.si as i64,
+        dst: kk.di as i64,
+        promote_to: kk.promote_to as i64,
+        ..Default::default()
+    })
+}
+
+// a legal move in standard algebraic notation, e.g. Nbd2, exd5, O-O or e8=Q+, for PGN files
+pub fn move_to_san(g: &mut Game, m: &Move) -> Result<String, EngineError> {
+    check_squares(m.src, m.dst)?;
+    let (si, di) = (m.src as i8, m.dst as i8);
+    if !legal_moves(g).iter().any(|el| {
+        el.si == si
+            && el.di == di
+            && (m.promote_to == VOID_ID || el.promote_to.abs() as i64 == m.promote_to.abs())
+    }) {
+        return Err(EngineError::IllegalMove(move_to_uci(m)));
+    }
+    let sf = g.board[si as usize];
+    let color = signum(sf) as Color;
+    if sf.abs() == KING_ID && chess960::is_castling(g, si, di) {
+        let mut result = String::from(if si > di { "O-O" } else { "O-O-O" });
+        result.push_str(check_suffix(g, m, color));
+        return Ok(result);
+    }
+    let to_uci = move_to_uci(m);
+    let capture = g.board[di as usize] != VOID_ID || (sf.abs() == PAWN_ID && col(si) != col(di));
+    let mut result = String::new();
+    if sf.abs() == PAWN_ID {
+        if capture {
+            result.push_str(&to_uci[0..1]);
+        }
+    } else {
+        result.push(FIG_STR[sf.unsigned_abs() as usize].as_bytes()[0] as char);
+        // other pieces of the same kind which can move to di
+        let others: Vec<KK> = legal_moves_of(g, color)
+            .into_iter()
+            .filter(|el| el.sf as i64 == sf && el.di == di && el.si != si)
+            .collect();
+        if !others.is_empty() {
+            if others.iter().all(|el| col(el.si) != col(si)) {
+                result.push_str(&to_uci[0..1]);
+            } else if others.iter().all(|el| row(el.si) != row(si)) {
+                result.push_str(&to_uci[1..2]);
+            } else {
+                result.push_str(&to_uci[0..2]);
+            }
+        }
+    }
+    if capture {
+        result.push('x');
+    }
+    result.push_str(&to_uci[2..4]);
+    if m.promote_to != VOID_ID {
+        result.push('=');
+        result.push(FIG_STR[m.promote_to.unsigned_abs() as usize].as_bytes()[0] as char);
+    } else if sf.abs() == PAWN_ID && base_row(di) {
+        result.push_str("=Q"); // do_move() promotes to a queen
+    }
+    result.push_str(check_suffix(g, m, color));
+    Ok(result)
+}
+
+// "+" or "#" when the move of color gives check or mate
+fn check_suffix(g: &mut Game, m: &Move, color: Color) -> &'static str {
+    let (board, pjm) = (g.board, g.pjm);
+    // as in do_move_unchecked(), an en passant capture may answer a check by a double pawn push
+    g.pjm = if is_a_pawn_at(g, m.src as i8) && (m.src - m.dst).abs() == 16 {
+        ((m.src + m.dst) / 2) as i8
+    } else {
+        -1
+    };
+    do_move_unchecked(g, m.src as i8, m.dst as i8, true);
+    if m.promote_to != VOID_ID {
+        g.board[m.dst as usize] = m.promote_to;
+    }
+    let opp = opp_color(color);
+    let result = if !in_check(g, king_pos(g, opp), opp, true) {
+        ""
+    } else if legal_moves_of(g, opp).is_empty() {
+        "#"
+    } else {
+        "+"
+    };
+    g.board = board;
+    g.pjm = pjm;
+    result
+}
+
+// the reason why a game ended, see game_over()
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum GameOver {
+    Checkmate, // the side to move is mated
+    Stalemate,
+    Repetition, // the third occurrence of a position after the first move
+    FiftyMoves,
+    InsufficientMaterial, // only kings, or one knight or bishop in addition
+    KingOfTheHill,        // the king of the other side reached the center
+    ThreeChecks,          // the side to move was checked for the third time
+}
+
+impl GameOver {
+    // the side to move has lost, else the game is a draw
+    pub fn lost(self) -> bool {
+        matches!(
+            self,
+            GameOver::Checkmate | GameOver::KingOfTheHill | GameOver::ThreeChecks
+        )
+    }
+}
+
+// None when the side to move can continue the game
+pub fn game_over(g: &mut Game) -> Option<GameOver> {
+    let color = -(g.move_counter as Color % 2) * 2 + 1;
+    if let Some(over) = variant::game_over(g, color) {
+        return Some(over);
+    }
+    if legal_moves(g).is_empty() {
+        return Some(if in_check(g, king_pos(g, color), color, true) {
+            GameOver::Checkmate
+        } else {
+            GameOver::Stalemate
+        });
+    }
+    if g.to_100 >= 100 {
+        return Some(GameOver::FiftyMoves);
+    }
+    if g.history
+        .get(&encode_board(g, color))
+        .is_some_and(|&n| n >= 3)
+    {
+        return Some(GameOver::Repetition);
+    }
+    let mut minor = 0;
+    for f in g.board {
+        match f.abs() {
+            VOID_ID | KING_ID => {}
+            KNIGHT_ID | BISHOP_ID => minor += 1,
+            _ => return None,
+        }
+    }
+    if minor <= 1 && g.variant == Variant::Standard {
+        // the other variants have more ways to win
+        return Some(GameOver::InsufficientMaterial);
+    }
+    None
+}
+
+// do_move() for a move with known promotion piece, e.g. from move_from_uci() or reply().
+// A promotion without a piece promotes to a queen.
+pub fn do_move_promote(g: &mut Game, m: &Move) -> Result<i32, EngineError> {
+    check_squares(m.src, m.dst)?;
+    if !legal_moves(g).iter().any(|el| {
+        el.si as i64 == m.src
+            && el.di as i64 == m.dst
+            && (m.promote_to == VOID_ID || el.promote_to as i64 == m.promote_to)
+    }) {
+        return Err(EngineError::IllegalMove(move_to_uci(m)));
+    }
+    let flag = do_move_unchecked(g, m.src as i8, m.dst as i8, false);
+    if m.promote_to != VOID_ID {
+        g.board[m.dst as usize] = m.promote_to; // do_move() always promotes to a queen
+    }
+    record_move(g, m.src as i8, m.dst as i8, flag);
+    Ok(flag)
+}
+
+pub const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+const MAX_FULL_MOVES: u16 = 10000; // larger move numbers of a FEN are clamped, move_counter is a u16
+
+// set up a position given in Forsyth-Edwards Notation
+pub fn set_fen(g: &mut Game, fen: &str) -> Result<(), EngineError> {
+    let fields: Vec<&str> = fen.split_whitespace().collect();
+    if fields.len() < 4 {
+        return Err(EngineError::InvalidFen(format!(
+            "FEN needs at least 4 fields: {}",
+            fen
+        )));
+    }
+    let mut board: Board = [VOID_ID; 64];
+    let ranks: Vec<&str> = fields[0].split('/').collect();
+    if ranks.len() != 8 {
+        return Err(EngineError::InvalidFen(format!(
+            "FEN needs 8 ranks: {}",
+            fields[0]
+        )));
+    }
+    for (i, rank) in ranks.iter().enumerate() {
+        let r = 7 - i;
+        let mut file = 0;
+        for c in rank.chars() {
+            if let Some(d) = c.to_digit(10) {
+                file += d as usize;
+                continue;
+            }
+            let f = match c.to_ascii_lowercase() {
+                'p' => PAWN_ID,
+                'n' => KNIGHT_ID,
+                'b' => BISHOP_ID,
+                'r' => ROOK_ID,
+                'q' => QUEEN_ID,
+                'k' => KING_ID,
+                _ => {
+                    return Err(EngineError::InvalidFen(format!(
+                        "invalid piece {} in FEN",
+                        c
+                    )))
+                }
+            };
+            if file > 7 {
+                return Err(EngineError::InvalidFen(format!("rank too long: {}", rank)));
+            }
+            board[board_pos(7 - file, r)] = if c.is_ascii_uppercase() { f } else { -f };
+            file += 1;
+        }
+        if file != 8 {
+            return Err(EngineError::InvalidFen(format!(
+                "rank has not 8 squares: {}",
+                rank
+            )));
+        }
+    }
+    if board.iter().filter(|&&f| f == W_KING).count() != 1
+        || board.iter().filter(|&&f| f == B_KING).count() != 1
+    {
+        return Err(EngineError::InvalidFen(
+            "FEN needs one king of each color".to_string(),
+        ));
+    }
+    if (0..64).any(|p| base_row(p) && board[p as usize].abs() == PAWN_ID) {
+        return Err(EngineError::InvalidFen(
+            "pawns on the first or last rank".to_string(),
+        ));
+    }
+    let black = match fields[1] {
+        "w" => false,
+        "b" => true,
+        _ => {
+            return Err(EngineError::InvalidFen(format!(
+                "invalid side to move: {}",
+                fields[1]
+            )))
+        }
+    };
+    // KQkq, or the files of the castling rooks for Chess960
+    let (castling, has_moved, chess960) = chess960::parse_castling(&board, fields[2])?;
+    let invalid_ep =
+        || EngineError::InvalidFen(format!("invalid en passant square: {}", fields[3]));
+    let pjm = if fields[3] == "-" {
+        -1
+    } else {
+        let p = square_from_str(fields[3].as_bytes()).ok_or_else(invalid_ep)?;
+        // behind a pawn of the opponent which just moved two squares, on the 3rd or 6th rank
+        let (ep_row, d) = if black { (2, 8) } else { (5, -8) };
+        let pawn = if black { W_PAWN } else { B_PAWN };
+        if row(p) != ep_row
+            || board[(p + d) as usize] != pawn
+            || board[p as usize] != VOID_ID
+            || board[(p - d) as usize] != VOID_ID
+        {
+            return Err(invalid_ep());
+        }
+        p
+    };
+    // the side to move could capture the king
+    let opp = if black { COLOR_WHITE } else { COLOR_BLACK };
+    let backup = std::mem::replace(&mut g.board, board);
+    let check = in_check(g, king_pos(g, opp), opp, true);
+    g.board = backup;
+    if check {
+        return Err(EngineError::InvalidFen(
+            "the side not to move is in check".to_string(),
+        ));
+    }
+    let to_100 = fields
+        .get(4)
+        .and_then(|x| x.parse::<u8>().ok())
+        .unwrap_or(0);
+    let full_moves = fields
+        .get(5)
+        .and_then(|x| x.parse::<u32>().ok())
+        .unwrap_or(1)
+        .clamp(1, MAX_FULL_MOVES as u32) as u16;
+    reset_game(g);
+    g.board = board;
+    g.has_moved = has_moved;
+    g.castling = castling;
+    g.chess960 = chess960;
+    g.pjm = pjm;
+    g.to_100 = to_100;
+    g.move_counter = (full_moves - 1) * 2 + black as u16;
+    g.start_fen = fields.join(" ");
+    Ok(())
 }
 
 fn board_pos(col: usize, row: usize) -> usize {
@@ -2648,3 +3759,478 @@ when false:
 
 */
 // 2647 lines 432 as
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    pub(super) fn game(fen: &str) -> Game {
+        let mut g = new_game();
+        g.verbose = false;
+        set_fen(&mut g, fen).unwrap();
+        g
+    }
+
+    #[test]
+    fn fixed_depth_search_is_reproducible() {
+        let limits = SearchLimits {
+            max_depth: 4,
+            ..Default::default()
+        };
+        let fen = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3";
+        let mut runs = Vec::new();
+        for _ in 0..2 {
+            let mut g = game(fen);
+            let r = reply(&mut g, &limits).unwrap();
+            runs.push((move_to_uci(&r.best), r.best.score, r.stats.nodes));
+        }
+        assert!(runs[0].2 > 0);
+        assert_eq!(runs[0], runs[1]);
+    }
+
+    #[test]
+    fn null_windows_keep_the_result() {
+        // at a fixed depth the null window searches and the reductions with their re-searches
+        // find the move of the full window search, and the same mate score. Other scores may
+        // differ a little, as the move order changes what the transposition table provides.
+        let mut g = game(START_FEN);
+        let limits = SearchLimits {
+            max_depth: 4,
+            ..Default::default()
+        };
+        for fen in [
+            "r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5Q2/PPPP1PPP/RNB1K1NR w KQkq - 4 4",
+            "6k1/5ppp/8/8/8/2n5/5PPP/R5K1 w - - 0 1",
+            "r3k2r/ppp2ppp/2n5/3qp3/8/2N5/PPPP1PPP/R1BQK2R w KQkq - 0 1",
+            "r1b1kb1r/pppp1ppp/2n5/4p3/2B1n2q/3P1N2/PPP2PPP/RNBQK2R w KQkq - 0 1",
+        ] {
+            let mut runs = Vec::new();
+            for on in [true, false] {
+                set_fen(&mut g, fen).unwrap();
+                let mut params = g.params.clone();
+                params.search.pvs = on;
+                params.search.lmr = on;
+                set_params(&mut g, params); // which clears the transposition table
+                let r = reply(&mut g, &limits).unwrap();
+                runs.push((move_to_uci(&r.best), r.best.score));
+            }
+            assert_eq!(runs[0].0, runs[1].0, "{}", fen);
+            if runs[1].1 >= KING_VALUE_DIV_2 as i64 {
+                assert_eq!(runs[0].1, runs[1].1, "{}", fen);
+            }
+        }
+    }
+
+    #[test]
+    fn null_move_zugzwang() {
+        // without pieces zugzwang is common, so there is no null move
+        let mut g = game("8/5pk1/6p1/8/3P4/5K2/8/8 w - - 0 1");
+        let limits = SearchLimits {
+            max_depth: 6,
+            ..Default::default()
+        };
+        assert_eq!(reply(&mut g, &limits).unwrap().stats.null_move_cut, 0);
+        // a zugzwang test position, where Nxd5 is the best move. Without the verification
+        // search of the endgame the null move cutoffs miss it.
+        let fen = "8/8/1p1r1k2/p1pPN1p1/P3KnP1/1P6/8/3R4 b - - 0 1";
+        let mut moves = Vec::new();
+        for verify in [true, false] {
+            set_fen(&mut g, fen).unwrap();
+            let mut params = g.params.clone();
+            params.search.null_move_verify = verify;
+            set_params(&mut g, params);
+            let r = reply(&mut g, &limits).unwrap();
+            assert!(r.stats.null_move_cut > 0);
+            moves.push(move_to_uci(&r.best));
+        }
+        assert_eq!(moves[0], "f4d5");
+        assert_ne!(moves[1], "f4d5");
+    }
+
+    #[test]
+    fn aspiration_re_search() {
+        // Nxd5 wins the queen, which the searches with a narrow window find after re-searches
+        let mut g = game("r3k2r/ppp2ppp/2n5/3qp3/8/2N5/PPPP1PPP/R1BQK2R w KQkq - 0 1");
+        let limits = SearchLimits {
+            max_depth: 5,
+            ..Default::default()
+        };
+        let mut params = g.params.clone();
+        params.search.aspiration_window = 1;
+        set_params(&mut g, params);
+        let r = reply(&mut g, &limits).unwrap();
+        assert!(r.stats.re_search > 0);
+        assert_eq!(move_to_uci(&r.best), "c3d5");
+        assert!(r.best.score > 800);
+        // a mate found at the depth of the first aspiration window opens that side at once
+        set_fen(
+            &mut g,
+            "r2qkb1r/pp2nppp/3p4/2pNN1B1/2BnP3/3P4/PPP2PPP/R2bK2R w KQkq - 1 1",
+        )
+        .unwrap();
+        set_params(&mut g, EvalParams::default());
+        let r = reply(&mut g, &limits).unwrap();
+        assert_eq!(move_to_uci(&r.best), "d5f6");
+        assert_eq!(r.best.score, KING_VALUE as i64 - 2);
+        assert_eq!(r.stats.re_search, 1);
+    }
+
+    #[test]
+    fn allocate_time_uses_the_clock() {
+        let mut g = game(START_FEN);
+        let mut c = Clock {
+            remaining: [60.0, 1.0],
+            increment: [0.0, 0.0],
+            moves_to_go: 0,
+        };
+        g.clock = Some(c);
+        let (nominal, hard) = allocate_time(&g);
+        let left = 60.0 - MOVE_OVERHEAD;
+        assert!((nominal - left / EXPECTED_GAME_LENGTH as f32).abs() < 1e-3);
+        assert!((hard - left * 0.4).abs() < 1e-3);
+        // the increment is added, mostly
+        c.increment = [2.0, 0.0];
+        g.clock = Some(c);
+        let (with_increment, _) = allocate_time(&g);
+        assert!((with_increment - nominal - 1.6).abs() < 1e-3);
+        // the time is shared by the moves to the time control, the last one may use more
+        c.moves_to_go = 10;
+        g.clock = Some(c);
+        assert!((allocate_time(&g).0 - (left / 10.0 + 1.6)).abs() < 1e-3);
+        c.moves_to_go = 1;
+        g.clock = Some(c);
+        assert_eq!(allocate_time(&g), (left * 0.8, left * 0.8));
+        // never more than the remaining time, even with a large increment
+        for remaining in [0.01, 0.1, 1.0, 5.0] {
+            for moves_to_go in [0, 1, 2, 40] {
+                c = Clock {
+                    remaining: [remaining, 60.0],
+                    increment: [30.0, 0.0],
+                    moves_to_go,
+                };
+                g.clock = Some(c);
+                let (nominal, hard) = allocate_time(&g);
+                assert!(0.0 < nominal && nominal <= hard && hard <= remaining.max(0.01));
+            }
+        }
+        // Black uses its own clock
+        set_fen(&mut g, "4k3/8/8/8/8/8/8/4K3 b - - 0 1").unwrap();
+        g.clock = Some(c);
+        assert!(allocate_time(&g).1 > 10.0);
+    }
+
+    #[test]
+    fn tapered_evaluation() {
+        let mut g = game(START_FEN);
+        assert_eq!(game_phase(&g), PHASE_MAX);
+        assert_eq!(taper(100, -60, PHASE_MAX), 100);
+        assert_eq!(taper(100, -60, 0), -60);
+        assert_eq!(taper(100, -60, PHASE_MAX / 2), 20);
+        // a bare pawn ending uses the endgame values only
+        set_fen(&mut g, "4k3/pppp4/8/8/8/8/PPPP4/4K3 w - - 0 1").unwrap();
+        assert_eq!(game_phase(&g), 0);
+        set_fen(&mut g, "4k3/8/8/8/8/8/8/3QK3 w - - 0 1").unwrap();
+        let phase = g.params.queen_value as i32 * PHASE_MAX / (2 * full_piece_material(&g));
+        assert_eq!(game_phase(&g), phase);
+    }
+
+    #[test]
+    fn mop_up_tables() {
+        let k = (B_KING + ARRAY_BASE_6) as usize;
+        let centre = square_from_str(b"e5").unwrap() as usize;
+        let corner = square_from_str(b"a8").unwrap() as usize;
+        let mut g = game("8/8/8/4k3/8/8/8/KR6 w - - 0 1");
+        let table = g.freedom_eg[k];
+        // the rook drives the Black king to the border
+        g.mop_up = setup_mop_up(&mut g);
+        assert!(g.mop_up);
+        assert!(g.freedom_eg[k][corner] < g.freedom_eg[k][centre]);
+        let in_centre = evaluate_board(&mut g);
+        set_fen(&mut g, "k7/8/8/8/8/8/8/KR6 w - - 0 1").unwrap();
+        // set_fen restores the normal tables
+        assert!(!g.mop_up);
+        assert_eq!(g.freedom_eg[k], table);
+        g.mop_up = setup_mop_up(&mut g);
+        assert!(evaluate_board(&mut g) > in_centre);
+        // not with pawns, and the tables of an earlier mop up are restored
+        set_fen(&mut g, "8/8/8/4k3/8/8/P7/KR6 w - - 0 1").unwrap();
+        g.mop_up = true;
+        assert!(!setup_mop_up(&mut g));
+        assert_eq!(g.freedom_eg[k], table);
+    }
+
+    #[test]
+    fn draw_score_with_contempt() {
+        let mut g = game(START_FEN);
+        g.root_color = COLOR_WHITE;
+        assert_eq!(draw_score(&g, COLOR_WHITE), 0);
+        // White at the root avoids the draw, so it is a gain for Black
+        set_style(&mut g, [50, 20], [100, 100]);
+        assert_eq!(draw_score(&g, COLOR_WHITE), -50);
+        assert_eq!(draw_score(&g, COLOR_BLACK), 50);
+        // with Black at the root its own contempt counts
+        g.root_color = COLOR_BLACK;
+        assert_eq!(draw_score(&g, COLOR_BLACK), -20);
+        assert_eq!(draw_score(&g, COLOR_WHITE), 20);
+        // a negative contempt seeks the draw
+        set_style(&mut g, [0, -30], [100, 100]);
+        assert_eq!(draw_score(&g, COLOR_BLACK), 30);
+    }
+
+    #[test]
+    fn mate_limited_search() {
+        // Rh1-h8# is only seen as a mate when the second ply finds no legal reply
+        let mut g = game("k7/8/1K6/8/8/8/8/7R w - - 0 1");
+        let limits = SearchLimits {
+            mate: 1,
+            ..Default::default()
+        };
+        let r = reply(&mut g, &limits).unwrap();
+        assert_eq!(move_to_uci(&r.best), "h1h8");
+        assert_eq!(r.best.score, KING_VALUE as i64);
+    }
+
+    #[test]
+    fn multi_pv_lines_share_the_time() {
+        let mut g = game(START_FEN);
+        let ranks = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let r = ranks.clone();
+        g.progress = Some(Box::new(move |info: &SearchInfo| {
+            r.lock().unwrap().push(info.multi_pv)
+        }));
+        let limits = SearchLimits {
+            movetime: 1.0,
+            ..Default::default()
+        };
+        let start = Instant::now();
+        let lines = reply_multi_pv(&mut g, &limits, 3);
+        // each line of its own would stop new iterations only after 0.7 s
+        assert!(start.elapsed() < Duration::from_secs_f32(1.6));
+        assert_eq!(lines.len(), 3);
+        assert!(lines.windows(2).all(|l| l[0].score >= l[1].score));
+        assert!(ranks.lock().unwrap().iter().all(|r| (1..=3).contains(r)));
+    }
+
+    #[test]
+    fn stats_of_the_last_info() {
+        let mut g = game("r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 3 3");
+        let infos = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let i = infos.clone();
+        g.progress = Some(Box::new(move |info: &SearchInfo| {
+            i.lock()
+                .unwrap()
+                .push((info.depth, info.nodes, info.stats.nodes))
+        }));
+        let limits = SearchLimits {
+            max_depth: 5,
+            ..Default::default()
+        };
+        let r = reply(&mut g, &limits).unwrap();
+        let infos = infos.lock().unwrap();
+        assert_eq!(
+            infos.iter().map(|i| i.0).collect::<Vec<_>>(),
+            [1, 2, 3, 4, 5]
+        );
+        assert!(infos.windows(2).all(|i| i[0].1 < i[1].1));
+        let (_, nodes, stats_nodes) = *infos.last().unwrap();
+        assert_eq!(nodes, stats_nodes);
+        assert_eq!(r.stats.nodes, nodes);
+    }
+
+    #[test]
+    fn engine_errors() {
+        let mut g = game(START_FEN);
+        assert_eq!(do_move(&mut g, 64, 0), Err(EngineError::InvalidSquare(64)));
+        assert_eq!(do_move(&mut g, -1, 0), Err(EngineError::InvalidSquare(-1)));
+        // e2e5, and a black move with White to move
+        assert!(matches!(
+            do_move(&mut g, 11, 35),
+            Err(EngineError::IllegalMove(_))
+        ));
+        assert!(matches!(
+            do_move(&mut g, 51, 43),
+            Err(EngineError::IllegalMove(_))
+        ));
+        let m = Move {
+            src: 11,
+            dst: 27,
+            promote_to: KNIGHT_ID,
+            ..Default::default()
+        };
+        assert!(matches!(
+            do_move_promote(&mut g, &m),
+            Err(EngineError::IllegalMove(_))
+        ));
+        assert!(matches!(
+            move_from_uci(&mut g, "e2e4x"),
+            Err(EngineError::InvalidMove(_))
+        ));
+        assert!(matches!(
+            move_from_uci(&mut g, "e2"),
+            Err(EngineError::InvalidMove(_))
+        ));
+        assert!(matches!(
+            move_from_uci(&mut g, "e1e2"),
+            Err(EngineError::IllegalMove(_))
+        ));
+        let m = Move {
+            src: 70,
+            dst: 27,
+            ..Default::default()
+        };
+        assert_eq!(move_to_san(&mut g, &m), Err(EngineError::InvalidSquare(70)));
+        let m = Move {
+            src: 11,
+            dst: 35,
+            ..Default::default()
+        };
+        assert!(matches!(
+            move_to_san(&mut g, &m),
+            Err(EngineError::IllegalMove(_))
+        ));
+        assert_eq!(
+            move_to_str(&g, 11, 64, FLAG_PLAIN),
+            Err(EngineError::InvalidSquare(64))
+        );
+        assert_eq!(
+            castling_moves(&g, 3, 70),
+            Err(EngineError::InvalidSquare(70))
+        );
+        assert!(matches!(
+            castling_moves(&g, 3, 11),
+            Err(EngineError::IllegalMove(_))
+        ));
+        // the failing calls left the game unchanged
+        assert_eq!((g.board, g.move_counter, g.played.len()), (SETUP, 0, 0));
+        assert_eq!(do_move(&mut g, 11, 27), Ok(FLAG_PLAIN));
+        assert_eq!(g.played.len(), 1);
+
+        let limits = SearchLimits {
+            max_depth: 2,
+            ..Default::default()
+        };
+        for fen in [
+            "k7/1Q6/1K6/8/8/8/8/8 b - - 0 1", // checkmate
+            "k7/8/1QK5/8/8/8/8/8 b - - 0 1",  // stalemate
+        ] {
+            set_fen(&mut g, fen).unwrap();
+            assert_eq!(
+                reply(&mut g, &limits).unwrap_err(),
+                EngineError::NoLegalMove
+            );
+        }
+    }
+
+    #[test]
+    fn set_fen_rejects_invalid_positions() {
+        let mut g = game(START_FEN);
+        for fen in [
+            "",
+            "8/8/8/8/8/8/8/8 w - -",
+            "4k3/8/8/8/8/8/8/4K3",
+            "4k3/8/8/8/8/8/4K3 w - -",
+            "4k3/8/8/8/8/8/8/4K4 w - -",
+            "4k3/8/8/8/8/8/8/3K3 w - -",
+            "4k3/8/8/8/8/8/8/4X3 w - -",
+            "4k3/8/8/8/8/8/8/4KK2 w - -",
+            "4k3/8/8/8/8/8/8/4K3 x - -",
+            "4k3/8/8/8/8/8/8/4K3 w - e9",
+            "P3k3/8/8/8/8/8/8/4K3 w - -",
+            "4k3/8/8/8/8/8/8/p3K3 w - -",
+            "4k3/8/8/8/8/8/8/4R1K1 w - -",  // Black is in check
+            "4k3/8/8/8/4P3/8/8/4K3 w - e3", // White to move
+            "4k3/8/8/8/4P3/8/8/4K3 b - e4", // not behind the pawn
+            "4k3/8/8/8/8/8/4P3/4K3 b - e3", // no double step
+            "4k3/8/8/8/4P3/4B3/8/4K3 b - e3",
+        ] {
+            assert!(
+                matches!(set_fen(&mut g, fen), Err(EngineError::InvalidFen(_))),
+                "{}",
+                fen
+            );
+        }
+        // a failing call leaves the game unchanged
+        assert_eq!(g.board, SETUP);
+    }
+
+    #[test]
+    fn set_fen_reads_the_move_counters() {
+        let mut g = game("4k3/8/8/8/8/8/8/4K3 b - - 12 40");
+        assert_eq!((g.to_100, g.move_counter), (12, 79));
+        set_fen(&mut g, "4k3/8/8/8/8/8/8/4K3 w - - 0 40000").unwrap();
+        assert_eq!(g.move_counter, (MAX_FULL_MOVES - 1) * 2);
+        set_fen(&mut g, "4k3/8/8/8/8/8/8/4K3 w - - 0 0").unwrap();
+        assert_eq!(g.move_counter, 0);
+    }
+
+    #[test]
+    fn move_from_san_parses_legal_moves() {
+        let mut g = game("r3k2r/1P6/8/3pP3/8/1N3N2/8/R3K2R w KQkq d6 0 1");
+        for (san, uci) in [
+            ("O-O", "e1g1"),
+            ("0-0-0+", "e1c1"),
+            ("Nc5", "b3c5"),
+            ("Nfd4", "f3d4"),
+            ("Nbd4", "b3d4"),
+            ("Nbd2", "b3d2"),
+            ("exd6", "e5d6"),
+            ("bxa8=Q+", "b7a8q"),
+            ("b8N", "b7b8n"),
+            ("Rh7!", "h1h7"),
+        ] {
+            let m = move_from_san(&mut g, san).expect(san);
+            assert_eq!(move_to_uci(&m), uci, "{}", san);
+        }
+        for san in ["Nd4", "b8=R", "Z9", "", "e"] {
+            assert!(
+                matches!(move_from_san(&mut g, san), Err(EngineError::InvalidMove(_))),
+                "{}",
+                san
+            );
+        }
+        for san in ["Nd2x", "Ke3", "exf6", "Qd1"] {
+            assert!(move_from_san(&mut g, san).is_err(), "{}", san);
+        }
+    }
+
+    #[test]
+    fn san_round_trip() {
+        let mut g = game(START_FEN);
+        for fen in [
+            START_FEN,
+            "r3k2r/1P6/8/3pP3/8/1N3N2/8/R3K2R w KQkq d6 0 1",
+            "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4",
+            "4k3/8/8/8/8/8/1R6/RR2K2R w K - 0 1",
+        ] {
+            set_fen(&mut g, fen).unwrap();
+            for el in legal_moves(&mut g) {
+                let m = Move {
+                    src: el.si as i64,
+                    dst: el.di as i64,
+                    promote_to: el.promote_to as i64,
+                    ..Default::default()
+                };
+                let san = move_to_san(&mut g, &m).unwrap();
+                let back = move_from_san(&mut g, &san).expect(&san);
+                assert_eq!(move_to_uci(&back), move_to_uci(&m), "{}", san);
+            }
+        }
+        set_fen(
+            &mut g,
+            "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4",
+        )
+        .unwrap();
+        let m = move_from_san(&mut g, "Qxf7").unwrap();
+        assert_eq!(move_to_san(&mut g, &m).unwrap(), "Qxf7#");
+    }
+
+    #[test]
+    fn en_passant_answers_a_check() {
+        let mut g = game("8/8/R7/5k2/3P1p2/2N5/4P3/K5R1 w - - 0 1");
+        let m = move_from_uci(&mut g, "e2e4").unwrap();
+        assert_eq!(move_to_san(&mut g, &m).unwrap(), "e4+");
+        do_move_promote(&mut g, &m).unwrap();
+        assert_eq!(game_over(&mut g), None);
+        assert!(move_from_uci(&mut g, "f4e3").is_ok());
+    }
+}
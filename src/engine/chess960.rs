@@ -0,0 +1,276 @@
+// Chess960 (Fischer Random Chess): castling with arbitrary king and rook start files, and the
+// 960 start positions by their standard index.
+// The start squares of the kings and of the castling rooks are kept in Game::castling. set_fen()
+// takes them from the castling field, as X-FEN (KQkq, the outermost rook of that side) or as
+// Shredder-FEN (the files of the rooks, e.g. HAha). After castling king and rook stand on the
+// G and F files, or on the C and D files, as in standard chess.
+// In Chess960 a castling is encoded as the king capturing its own rook, as for UCI_Chess960,
+// otherwise as the king moving two squares, so nothing changes for standard chess.
+
+use super::*;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(super) struct CastlingSquares {
+    pub(super) king: [Position; 2],      // White, Black
+    pub(super) rook: [[Position; 2]; 2], // by color and side, side 0 is the king side towards the H file
+}
+
+pub(super) const STANDARD_CASTLING: CastlingSquares = CastlingSquares {
+    king: [3, 59],
+    rook: [[0, 7], [56, 63]],
+};
+
+// king and rook of the castling si -> di, as (start, destination) pairs, e.g. to move the figures
+// of the GUI. si must be a king square of the back rank, and di on the same rank.
+pub fn castling_moves(
+    g: &Game,
+    si: Position,
+    di: Position,
+) -> Result<[(Position, Position); 2], EngineError> {
+    check_squares(si as i64, di as i64)?;
+    if si == di || row(si) != row(di) || !base_row(si) {
+        let m = Move {
+            src: si as i64,
+            dst: di as i64,
+            ..Default::default()
+        };
+        return Err(EngineError::IllegalMove(move_to_uci(&m)));
+    }
+    Ok(castling_moves_unchecked(g, si, di))
+}
+
+// the caller ensures valid squares
+pub(super) fn castling_moves_unchecked(
+    g: &Game,
+    si: Position,
+    di: Position,
+) -> [(Position, Position); 2] {
+    let c = (si >= 56) as usize;
+    let side = (di > si) as usize;
+    let rs = if (di - si).abs() == 2 {
+        g.castling.rook[c][side] // in Chess960 this is di as well
+    } else {
+        di
+    };
+    let base = si - col(si);
+    if side == 0 {
+        [(si, base + 1), (rs, base + 2)]
+    } else {
+        [(si, base + 5), (rs, base + 4)]
+    }
+}
+
+// true for a king move si -> di which is a castling, before the move is done
+pub(super) fn is_castling(g: &Game, si: Position, di: Position) -> bool {
+    is_a_king_at(g, si)
+        && ((si - di).abs() == 2 || signum(g.board[di as usize]) == signum(g.board[si as usize]))
+}
+
+// put king and rook of a castling on their destinations, or back to their start squares
+pub(super) fn castle(board: &mut Board, moves: [(Position, Position); 2], undo: bool) {
+    let [(ks, kd), (rs, rd)] = moves;
+    let (from, to) = if undo {
+        ([kd, rd], [ks, rs])
+    } else {
+        ([ks, rs], [kd, rd])
+    };
+    let pieces = [board[from[0] as usize], board[from[1] as usize]];
+    board[from[0] as usize] = VOID_ID;
+    board[from[1] as usize] = VOID_ID;
+    board[to[0] as usize] = pieces[0];
+    board[to[1] as usize] = pieces[1];
+}
+
+// the castlings of color when all squares between king and rook and their destinations are free.
+// Castling rights and attacked squares are tested by the callers.
+pub(super) fn walk_castlings(g: &Game, color: Color, mut kk: KK, s: &mut Vec<KK>) {
+    let c = (color == COLOR_BLACK) as usize;
+    let ks = g.castling.king[c];
+    if g.board[ks as usize] != W_KING * color {
+        return;
+    }
+    kk.si = ks;
+    kk.sf = (W_KING * color) as i8;
+    kk.df = VOID_ID as i8;
+    for side in 0..2 {
+        let rs = g.castling.rook[c][side];
+        if g.board[rs as usize] != W_ROOK * color {
+            continue;
+        }
+        let [(_, kd), (_, rd)] = castling_moves_unchecked(g, ks, rs);
+        let low = min(min(ks, kd), min(rs, rd));
+        let high = max(max(ks, kd), max(rs, rd));
+        if (low..=high).all(|p| p == ks || p == rs || g.board[p as usize] == VOID_ID) {
+            kk.di = if !g.chess960 && (kd - ks).abs() == 2 {
+                kd
+            } else {
+                rs
+            };
+            s.push(kk);
+        }
+    }
+}
+
+// the squares the king passes, which must not be attacked, including start and destination
+pub(super) fn king_path(moves: [(Position, Position); 2]) -> std::ops::RangeInclusive<Position> {
+    let (ks, kd) = moves[0];
+    min(ks, kd)..=max(ks, kd)
+}
+
+// the castling squares and the has_moved marks for the castling field of a FEN, for set_fen().
+// A missing right marks the rook, or the king for both sides, as moved, and rights without a rook
+// on the back rank are ignored. The bool is true for Chess960 castling squares and for
+// Shredder-FEN file letters.
+pub(super) fn parse_castling(
+    board: &Board,
+    field: &str,
+) -> Result<(CastlingSquares, HasMoved, bool), EngineError> {
+    let mut squares = STANDARD_CASTLING;
+    let mut rights = [[false; 2]; 2];
+    let mut letters = false;
+    for ch in field.chars().filter(|&ch| ch != '-') {
+        let c = ch.is_ascii_lowercase() as usize;
+        let color = 1 - 2 * c as Color;
+        let base = 56 * c;
+        let Some(king) = (base..base + 8).find(|&p| board[p] == W_KING * color) else {
+            continue; // the king has left the back rank
+        };
+        let rook = match ch.to_ascii_uppercase() {
+            'K' => (base..king).find(|&p| board[p] == W_ROOK * color),
+            'Q' => (king + 1..base + 8)
+                .rev()
+                .find(|&p| board[p] == W_ROOK * color),
+            f @ 'A'..='H' => {
+                letters = true;
+                let p = base + 7 - (f as u8 - b'A') as usize;
+                (board[p] == W_ROOK * color).then_some(p)
+            }
+            _ => {
+                return Err(EngineError::InvalidFen(format!(
+                    "invalid castling field: {}",
+                    field
+                )))
+            }
+        };
+        if let Some(r) = rook {
+            let side = (r > king) as usize;
+            squares.king[c] = king as Position;
+            squares.rook[c][side] = r as Position;
+            rights[c][side] = true;
+        }
+    }
+    let mut has_moved = HasMoved::new();
+    for (c, rights) in rights.iter().enumerate() {
+        if *rights == [false; 2] {
+            has_moved.insert(squares.king[c]);
+        }
+        for (rook, right) in squares.rook[c].iter().zip(rights) {
+            if !right {
+                has_moved.insert(*rook);
+            }
+        }
+    }
+    Ok((squares, has_moved, letters || squares != STANDARD_CASTLING))
+}
+
+// the start position with the given index of the standard numbering, 518 is the standard position
+pub fn chess960_fen(index: usize) -> Result<String, EngineError> {
+    if index >= 960 {
+        return Err(EngineError::InvalidFen(format!(
+            "Chess960 index {} not in 0..960",
+            index
+        )));
+    }
+    // the two knights on the free squares left by bishops and queen
+    const KNIGHTS: [(usize, usize); 10] = [
+        (0, 1),
+        (0, 2),
+        (0, 3),
+        (0, 4),
+        (1, 2),
+        (1, 3),
+        (1, 4),
+        (2, 3),
+        (2, 4),
+        (3, 4),
+    ];
+    let mut rank = [b' '; 8]; // files a to h
+    let free = |rank: &[u8; 8], i: usize| (0..8).filter(|&f| rank[f] == b' ').nth(i).unwrap();
+    let mut n = index;
+    rank[2 * (n % 4) + 1] = b'B'; // light squares
+    n /= 4;
+    rank[2 * (n % 4)] = b'B';
+    n /= 4;
+    let q = free(&rank, n % 6);
+    rank[q] = b'Q';
+    n /= 6;
+    let (k1, k2) = KNIGHTS[n];
+    let (f1, f2) = (free(&rank, k1), free(&rank, k2));
+    rank[f1] = b'N';
+    rank[f2] = b'N';
+    for piece in [b'R', b'K', b'R'] {
+        let f = free(&rank, 0);
+        rank[f] = piece;
+    }
+    let white: String = rank.iter().map(|&b| b as char).collect();
+    // Shredder-FEN, the king side rook first
+    let rooks: String = (0..8)
+        .rev()
+        .filter(|&f| rank[f] == b'R')
+        .map(|f| (b'A' + f as u8) as char)
+        .collect();
+    Ok(format!(
+        "{}/pppppppp/8/8/8/8/PPPPPPPP/{} w {}{} - 0 1",
+        white.to_lowercase(),
+        white,
+        rooks,
+        rooks.to_lowercase()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::game;
+    use super::*;
+
+    #[test]
+    fn standard_start_position() {
+        let fen = chess960_fen(518).unwrap();
+        assert_eq!(fen, START_FEN.replace("KQkq", "HAha"));
+        let mut g = game(START_FEN);
+        let (board, castling, has_moved) = (g.board, g.castling, g.has_moved.0);
+        set_fen(&mut g, &fen).unwrap();
+        assert_eq!(
+            (g.board, g.castling, g.has_moved.0),
+            (board, castling, has_moved)
+        );
+        assert!(chess960_fen(960).is_err());
+    }
+
+    #[test]
+    fn no_castling_out_of_or_through_check() {
+        let limits = SearchLimits {
+            max_depth: 3,
+            ..Default::default()
+        };
+        // the castled rook would block the attacker on d1 or h1
+        let mut g = game("6k1/8/8/8/b7/4N3/PP6/RK5r w A - 0 1");
+        assert!(g.chess960);
+        for depth in 1..=limits.max_depth {
+            let l = SearchLimits {
+                max_depth: depth,
+                ..limits
+            };
+            assert_ne!(move_to_uci(&reply(&mut g, &l).unwrap().best), "b1a1");
+        }
+        set_fen(&mut g, "6k1/8/8/8/8/8/6PP/r5KR w H - 0 1").unwrap();
+        assert!(g.chess960);
+        for depth in 1..=limits.max_depth {
+            let l = SearchLimits {
+                max_depth: depth,
+                ..limits
+            };
+            assert_ne!(move_to_uci(&reply(&mut g, &l).unwrap().best), "g1h1");
+        }
+    }
+}
@@ -0,0 +1,168 @@
+// King safety: pawn shield and open files in front of the king, and attacks into the king zone.
+// All terms are scaled by the opponent's remaining piece material, so they fade out towards the endgame,
+// and weighted by the aggression of the attacking side.
+
+use super::*;
+
+const FILE_H: u64 = 0x0101010101010101; // col 0
+
+// weighted by the aggression of side
+fn aggressive(g: &Game, side: usize, v: i16) -> i16 {
+    (v as i32 * g.aggression[side] as i32 / 100) as i16
+}
+
+// scaled by the material, which the opponent of side can use for an attack
+fn scaled(g: &Game, material: [i32; 2], side: usize, v: i16) -> i16 {
+    let m = min(material[1 - side], full_piece_material(g));
+    (v as i32 * m / full_piece_material(g)) as i16
+}
+
+// penalty for a bad pawn shelter of the king of side, 0 is White
+fn shelter(g: &Game, pawns: [u64; 2], king: i8, side: usize) -> i16 {
+    let (c, r) = (col(king), row(king));
+    let dir: i8 = if side == 0 { 1 } else { -1 };
+    let back_row = if side == 0 { r <= 1 } else { r >= 6 };
+    let mut result = 0;
+    for f in max(c - 1, 0)..=min(c + 1, 7) {
+        let file = FILE_H << f;
+        result += if !back_row {
+            g.params.king_shield_pawn // the king has left its shelter
+        } else if pawns[side] & file & 0xFF << (8 * (r + dir)) != 0 {
+            0
+        } else if pawns[side] & file & 0xFF << (8 * (r + 2 * dir)) != 0 {
+            g.params.king_shield_pawn / 2
+        } else {
+            g.params.king_shield_pawn
+        };
+        if pawns[side] & file == 0 {
+            result += if pawns[1 - side] & file == 0 {
+                g.params.king_open_file
+            } else {
+                g.params.king_half_open_file
+            };
+        }
+    }
+    result
+}
+
+// result is for White
+pub(super) fn king_shelter(g: &Game) -> i16 {
+    let mut pawns = [0u64; 2];
+    let mut kings = [-1i8; 2];
+    for (p, f) in g.board.iter().enumerate() {
+        match *f {
+            W_PAWN => pawns[0] |= 1 << p,
+            B_PAWN => pawns[1] |= 1 << p,
+            W_KING => kings[0] = p as i8,
+            B_KING => kings[1] = p as i8,
+            _ => {}
+        }
+    }
+    if kings[0] < 0 || kings[1] < 0 {
+        return 0;
+    }
+    let material = piece_material(g);
+    // a weak shelter of the black king is a target for the attack of White, and vice versa
+    aggressive(g, 0, scaled(g, material, 1, shelter(g, pawns, kings[1], 1)))
+        - aggressive(g, 1, scaled(g, material, 0, shelter(g, pawns, kings[0], 0)))
+}
+
+// bonus for the side to move, for the squares around the opponent king which it attacks
+pub(super) fn king_zone_attacks(g: &Game, color: Color, control: &ChessSquares) -> i16 {
+    let king = match g.board.iter().position(|&f| f == -color * KING_ID) {
+        Some(p) => p,
+        None => return 0,
+    };
+    let mut zone = 1u64 << king;
+    for el in g.king_path[king].iter().take_while(|el| el.pos >= 0) {
+        zone |= 1 << el.pos;
+    }
+    let n = (control.0 & zone).count_ones() as i16;
+    let side = (color == COLOR_BLACK) as usize;
+    // the opponent of the attacked king is side
+    let v = scaled(
+        g,
+        piece_material(g),
+        1 - side,
+        n * g.params.king_zone_attack,
+    );
+    aggressive(g, side, v)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::game;
+    use super::*;
+
+    fn pawns_and_kings(g: &Game) -> ([u64; 2], [i8; 2]) {
+        let mut pawns = [0u64; 2];
+        let mut kings = [-1i8; 2];
+        for (p, f) in g.board.iter().enumerate() {
+            match *f {
+                W_PAWN => pawns[0] |= 1 << p,
+                B_PAWN => pawns[1] |= 1 << p,
+                W_KING => kings[0] = p as i8,
+                B_KING => kings[1] = p as i8,
+                _ => {}
+            }
+        }
+        (pawns, kings)
+    }
+
+    fn white_shelter(g: &mut Game, fen: &str) -> i16 {
+        set_fen(g, fen).unwrap();
+        let (pawns, kings) = pawns_and_kings(g);
+        shelter(g, pawns, kings[0], 0)
+    }
+
+    #[test]
+    fn shelter_terms() {
+        let mut g = game(START_FEN);
+        let shield = g.params.king_shield_pawn;
+        let fen = "6k1/5ppp/8/8/8/8/5PPP/6K1 w - - 0 1";
+        assert_eq!(white_shelter(&mut g, fen), 0);
+        let (pawns, kings) = pawns_and_kings(&g);
+        assert_eq!(shelter(&g, pawns, kings[1], 1), 0);
+        // the g pawn has advanced one square
+        let fen = "6k1/5ppp/8/8/8/6P1/5P1P/6K1 w - - 0 1";
+        assert_eq!(white_shelter(&mut g, fen), shield / 2);
+        // the g pawn is missing, the file is half open or open
+        let fen = "6k1/5ppp/8/8/8/8/5P1P/6K1 w - - 0 1";
+        let half_open = g.params.king_half_open_file;
+        assert_eq!(white_shelter(&mut g, fen), shield + half_open);
+        let fen = "6k1/5p1p/8/8/8/8/5P1P/6K1 w - - 0 1";
+        let open = g.params.king_open_file;
+        assert_eq!(white_shelter(&mut g, fen), shield + open);
+        // the king has left its shelter
+        let fen = "6k1/5ppp/8/8/8/6K1/5PPP/8 w - - 0 1";
+        assert_eq!(white_shelter(&mut g, fen), 3 * shield);
+    }
+
+    #[test]
+    fn shelter_fades_out() {
+        // the weak shelter of the White king counts only with Black pieces left for an attack
+        let mut g = game("r5k1/5ppp/8/8/8/8/5P1P/R5K1 w - - 0 1");
+        assert!(king_shelter(&g) < 0);
+        set_style(&mut g, [0, 0], [100, 0]);
+        assert_eq!(king_shelter(&g), 0);
+        set_style(&mut g, [0, 0], [100, 100]);
+        set_fen(&mut g, "6k1/5ppp/8/8/8/8/5P1P/6K1 w - - 0 1").unwrap();
+        assert_eq!(king_shelter(&g), 0);
+    }
+
+    #[test]
+    fn zone_attacks() {
+        let mut g = game("6k1/5ppp/8/8/8/8/5PPP/Q5K1 w - - 0 1");
+        let mut control = ChessSquares::new();
+        control.insert(square_from_str(b"a5").unwrap() as usize);
+        assert_eq!(king_zone_attacks(&g, COLOR_WHITE, &control), 0);
+        control.insert(square_from_str(b"f8").unwrap() as usize);
+        control.insert(square_from_str(b"h8").unwrap() as usize);
+        let v =
+            2 * g.params.king_zone_attack as i32 * piece_material(&g)[0] / full_piece_material(&g);
+        assert_eq!(king_zone_attacks(&g, COLOR_WHITE, &control), v as i16);
+        // without pieces there is no attack
+        set_fen(&mut g, "6k1/5ppp/8/8/8/8/5PPP/6K1 w - - 0 1").unwrap();
+        assert_eq!(king_zone_attacks(&g, COLOR_WHITE, &control), 0);
+    }
+}
@@ -0,0 +1,226 @@
+// Mate solver: proves or refutes a forced mate in n moves by an exhaustive search of all legal moves,
+// without evaluation, pruning by scores, or depth extensions.
+// The attacker tries checks first, the defender captures and king moves first. Positions where the
+// attacker has no mate in k moves are remembered, so transpositions are not searched again.
+// In the variants a win by their rules counts like a mate, for either side.
+
+use super::*;
+
+struct Solver {
+    no_mate: HashMap<Key, usize>, // the largest k for which the attacker has no mate in k
+}
+
+// the position with the castling rights and the en passant square, which encode_board() ignores
+type Key = (BitBuffer192, u8, i8);
+
+fn key(g: &Game, color: Color) -> Key {
+    let mut castling = 0;
+    for c in 0..2 {
+        for side in 0..2 {
+            if !g.has_moved.contains(g.castling.king[c])
+                && !g.has_moved.contains(g.castling.rook[c][side])
+            {
+                castling |= 1 << (2 * c + side);
+            }
+        }
+    }
+    (encode_board(g, color), castling, g.pjm)
+}
+
+// the state changed by make(), restored by unmake()
+struct Undo {
+    board: Board,
+    has_moved: HasMoved,
+    pjm: i8,
+    checks: [u8; 2],
+}
+
+fn make(g: &mut Game, m: &KK) -> Undo {
+    let undo = Undo {
+        board: g.board,
+        has_moved: g.has_moved,
+        pjm: g.pjm,
+        checks: g.checks,
+    };
+    g.has_moved.insert(m.si);
+    g.pjm = if is_a_pawn(m.sf) && (m.si - m.di).abs() == 16 {
+        (m.si + m.di) / 2
+    } else {
+        -1
+    };
+    do_move_unchecked(g, m.si, m.di, true);
+    if m.promote_to != VOID_ID as i8 {
+        g.board[m.di as usize] = m.promote_to as i64; // do_move() always promotes to a queen
+    }
+    variant::count_check(g, signum(m.sf as i64) as Color);
+    undo
+}
+
+fn unmake(g: &mut Game, undo: Undo) {
+    g.board = undo.board;
+    g.has_moved = undo.has_moved;
+    g.pjm = undo.pjm;
+    g.checks = undo.checks;
+}
+
+// a check, or a win by the variant rules
+fn check_or_win(g: &Game, color: Color) -> bool {
+    let opp = opp_color(color);
+    in_check(g, king_pos(g, opp), opp, true) || variant::lost(g, opp)
+}
+
+fn stopped(g: &Game) -> bool {
+    g.stop.load(Ordering::Relaxed)
+}
+
+// color is to move and mates in at most n moves, the result is the mating line
+fn attack(s: &mut Solver, g: &mut Game, color: Color, n: usize) -> Option<Vec<KK>> {
+    if variant::lost(g, color) {
+        return None; // by the last move of the defender
+    }
+    let key = key(g, color);
+    if s.no_mate.get(&key).is_some_and(|&k| k >= n) || stopped(g) {
+        return None;
+    }
+    g.stats.nodes += 1;
+    let moves = legal_moves_of(g, color);
+    let mut checks = Vec::with_capacity(moves.len());
+    for el in &moves {
+        let undo = make(g, el);
+        checks.push(check_or_win(g, color));
+        unmake(g, undo);
+    }
+    let mut order: Vec<usize> = (0..moves.len()).collect();
+    order.sort_by_key(|&i| (!checks[i], moves[i].df == VOID_ID as i8));
+    for i in order {
+        if n == 1 && !checks[i] {
+            break; // a mate is always a check, and a variant win is counted as one
+        }
+        let undo = make(g, &moves[i]);
+        let line = defend(s, g, opp_color(color), n);
+        unmake(g, undo);
+        if let Some(mut line) = line {
+            line.insert(0, moves[i]);
+            return Some(line);
+        }
+    }
+    if !stopped(g) {
+        let k = s.no_mate.entry(key).or_insert(0);
+        *k = max(*k, n);
+    }
+    None
+}
+
+// color is to move, the opponent mates in at most n moves including the one just done.
+// The result is the longest defence.
+fn defend(s: &mut Solver, g: &mut Game, color: Color, n: usize) -> Option<Vec<KK>> {
+    if variant::lost(g, color) {
+        return Some(Vec::new()); // won by the variant rules
+    }
+    let mut moves = legal_moves_of(g, color);
+    if moves.is_empty() {
+        if in_check(g, king_pos(g, color), color, true) {
+            return Some(Vec::new()); // checkmate
+        }
+        return None; // stalemate
+    }
+    if n == 1 {
+        return None;
+    }
+    moves.sort_by_key(|el| el.df == VOID_ID as i8 && !is_a_king(el.sf));
+    let mut best: Option<Vec<KK>> = None;
+    for el in &moves {
+        let undo = make(g, el);
+        let line = attack(s, g, opp_color(color), n - 1);
+        unmake(g, undo);
+        match line {
+            None => return None, // refuted
+            Some(mut line) => {
+                if best.as_ref().is_none_or(|b| line.len() + 1 > b.len()) {
+                    line.insert(0, *el);
+                    best = Some(line);
+                }
+            }
+        }
+    }
+    best
+}
+
+// the shortest forced mate in at most n moves for the side to move, as the line of moves of both
+// sides ending with the mate, where the defender resists as long as possible.
+// None when there is no such mate, or when the search was stopped.
+pub fn solve_mate(g: &mut Game, n: usize) -> Option<Vec<Move>> {
+    let color = ((g.move_counter as i64 + 1) % 2) * 2 - 1;
+    let mut s = Solver {
+        no_mate: HashMap::new(),
+    };
+    g.stats = Default::default();
+    for k in 1..=n {
+        if let Some(line) = attack(&mut s, g, color, k) {
+            let plies = line.len() as i64;
+            let mut sign = 1;
+            let mut result = Vec::with_capacity(line.len());
+            for (i, el) in line.iter().enumerate() {
+                // the mate score for the side doing the move, as in abeta()
+                result.push(Move {
+                    src: el.si as i64,
+                    dst: el.di as i64,
+                    promote_to: el.promote_to as i64,
+                    score: sign * (KING_VALUE as i64 - (plies - i as i64)),
+                    state: STATE_PLAYING,
+                    ..Default::default()
+                });
+                sign = -sign;
+            }
+            return Some(result);
+        }
+        if stopped(g) {
+            break;
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::game;
+    use super::*;
+
+    #[test]
+    fn mate_in_two() {
+        let mut g = game("k7/8/2K5/8/8/8/8/7R w - - 0 1");
+        assert!(solve_mate(&mut g, 1).is_none());
+        let line = solve_mate(&mut g, 2).unwrap();
+        assert_eq!(line.len(), 3);
+        assert!(line[0].score > KING_VALUE_DIV_2 as i64);
+        for m in &line {
+            do_move_promote(&mut g, m).unwrap();
+        }
+        assert_eq!(game_over(&mut g), Some(GameOver::Checkmate));
+    }
+
+    #[test]
+    fn variant_wins() {
+        let mut g = game(START_FEN);
+        let line =
+            |line: Option<Vec<Move>>| line.map(|l| l.iter().map(move_to_uci).collect::<Vec<_>>());
+        set_variant(&mut g, Variant::KingOfTheHill);
+        set_fen(&mut g, "k7/8/8/8/8/4K3/8/8 w - - 0 1").unwrap();
+        assert_eq!(line(solve_mate(&mut g, 1)), Some(vec!["e3e4".to_string()]));
+        set_variant(&mut g, Variant::ThreeCheck);
+        set_fen(&mut g, "4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        g.checks = [2, 0];
+        assert_eq!(line(solve_mate(&mut g, 1)), Some(vec!["a1a8".to_string()]));
+        set_variant(&mut g, Variant::Standard);
+        set_fen(&mut g, "4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        assert!(solve_mate(&mut g, 1).is_none());
+        // the rook checks before the mate, the third check of black
+        let fen = "k7/8/2K5/3Q4/8/8/8/1r6 w - - 0 1";
+        set_fen(&mut g, fen).unwrap();
+        assert_eq!(solve_mate(&mut g, 2).map(|l| l.len()), Some(3));
+        set_variant(&mut g, Variant::ThreeCheck);
+        set_fen(&mut g, fen).unwrap();
+        g.checks = [0, 2];
+        assert!(solve_mate(&mut g, 2).is_none());
+    }
+}
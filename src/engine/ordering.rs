@@ -0,0 +1,119 @@
+// Killer moves and history heuristic for the ordering of quiet moves.
+// Quiet moves which caused a beta cutoff are stored as killers for their ply, and their butterfly history
+// entry, indexed by color, source and destination square, is increased by the squared remaining depth.
+// Both give a bonus to the guessed rating of quiet moves when abeta() generates a new move list.
+
+use super::*;
+
+const KILLERS: usize = 2; // per ply
+const KILLER_BONUS: [i16; KILLERS] = [20, 15]; // about the larger freedom deltas, larger values needed more nodes
+const HISTORY_DIV: i32 = 64; // history counts per bonus centipawn
+const MAX_HISTORY_BONUS: i16 = 40;
+const MAX_HISTORY: i32 = 1 << 20; // all counts are halved when one grows beyond
+
+pub(super) type Killers = [[(i8, i8); KILLERS]; 64]; // (si, di), indexed by cup like move_chain
+pub(super) type Butterfly = [[[i32; 64]; 64]; 2]; // indexed by col_idx(), si, di
+
+fn quiet(el: &KK) -> bool {
+    el.df == VOID_ID as i8 && el.promote_to == 0
+}
+
+// at the start of each reply(), old killers are useless, and old history counts fade out
+pub(super) fn new_search(g: &mut Game) {
+    g.killers = [[(-1, -1); KILLERS]; 64];
+    for el in g.butterfly.iter_mut().flatten().flatten() {
+        *el /= 8;
+    }
+}
+
+// bonus for the guessed rating of a quiet move at ply cup
+pub(super) fn quiet_bonus(g: &Game, color: Color, cup: i64, el: &KK) -> i16 {
+    if !quiet(el) {
+        return 0;
+    }
+    if let Some(i) = g.killers[cup as usize]
+        .iter()
+        .position(|&k| k == (el.si, el.di))
+    {
+        return KILLER_BONUS[i];
+    }
+    let h = g.butterfly[col_idx(color) as usize][el.si as usize][el.di as usize];
+    min(h / HISTORY_DIV, MAX_HISTORY_BONUS as i32) as i16
+}
+
+// the move el caused a beta cutoff at ply cup
+pub(super) fn update(g: &mut Game, color: Color, cup: i64, depth_0: usize, el: &KK) {
+    if !quiet(el) {
+        return;
+    }
+    let k = &mut g.killers[cup as usize];
+    if k[0] != (el.si, el.di) {
+        k[1] = k[0];
+        k[0] = (el.si, el.di);
+    }
+    let h = &mut g.butterfly[col_idx(color) as usize][el.si as usize][el.di as usize];
+    *h += (depth_0 * depth_0) as i32;
+    if *h > MAX_HISTORY {
+        for el in g.butterfly.iter_mut().flatten().flatten() {
+            *el /= 2;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::game;
+    use super::*;
+
+    fn kk(m: &[u8], df: i8) -> KK {
+        KK {
+            si: square_from_str(m).unwrap(),
+            di: square_from_str(&m[2..]).unwrap(),
+            df,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn killers_and_history() {
+        let mut g = game(START_FEN);
+        new_search(&mut g);
+        let w = col_idx(COLOR_WHITE) as usize;
+        let (a, b) = (kk(b"e2e4", VOID_ID as i8), kk(b"g1f3", VOID_ID as i8));
+        update(&mut g, COLOR_WHITE, 3, 4, &a);
+        assert_eq!(quiet_bonus(&g, COLOR_WHITE, 3, &a), KILLER_BONUS[0]);
+        // the newest killer comes first, and a killer is not stored twice
+        update(&mut g, COLOR_WHITE, 3, 4, &b);
+        update(&mut g, COLOR_WHITE, 3, 4, &b);
+        assert_eq!(g.killers[3], [(b.si, b.di), (a.si, a.di)]);
+        assert_eq!(quiet_bonus(&g, COLOR_WHITE, 3, &a), KILLER_BONUS[1]);
+        // at other plies the history counts, the squared depth of each cutoff
+        assert_eq!(g.butterfly[w][b.si as usize][b.di as usize], 32);
+        update(&mut g, COLOR_WHITE, 5, 40, &a);
+        assert_eq!(g.killers[5][0], (a.si, a.di));
+        assert_eq!(
+            quiet_bonus(&g, COLOR_WHITE, 6, &a),
+            (1616 / HISTORY_DIV) as i16
+        );
+        assert_eq!(quiet_bonus(&g, COLOR_BLACK, 6, &a), 0);
+        // captures are ordered by their exchange, not by the history
+        let c = kk(b"e4d5", B_PAWN as i8);
+        update(&mut g, COLOR_WHITE, 7, 10, &c);
+        assert_eq!(g.killers[7][0], (-1, -1));
+        assert_eq!(g.butterfly[w][c.si as usize][c.di as usize], 0);
+        assert_eq!(quiet_bonus(&g, COLOR_WHITE, 3, &c), 0);
+        // the bonus is limited, and large counts are halved
+        g.butterfly[w][a.si as usize][a.di as usize] = MAX_HISTORY;
+        assert_eq!(quiet_bonus(&g, COLOR_WHITE, 6, &a), MAX_HISTORY_BONUS);
+        update(&mut g, COLOR_WHITE, 3, 1, &a);
+        assert_eq!(
+            g.butterfly[w][a.si as usize][a.di as usize],
+            (MAX_HISTORY + 1) / 2
+        );
+        assert_eq!(g.butterfly[w][b.si as usize][b.di as usize], 16);
+        // a new search forgets the killers and fades the history
+        new_search(&mut g);
+        assert_eq!(g.killers[3][0], (-1, -1));
+        assert_eq!(g.butterfly[w][b.si as usize][b.di as usize], 2);
+    }
+}
@@ -0,0 +1,325 @@
+// Evaluation parameters and search switches, loaded from a TOML or JSON file.
+// Entries missing in the file keep their default values, which are the built-in values of the engine.
+//
+// pawn_value = 100
+// knight_value = 300
+// ...
+// [extend]
+// check = false
+
+use super::*;
+use serde::{Deserialize, Serialize};
+
+const MAX_PIECE_VALUE: i16 = 1500;
+const MAX_MATERIAL: i32 = KING_VALUE_DIV_2 as i32 - 2000; // the rest is left for the positional terms
+const MAX_TERM: i16 = 200; // bound of the positional parameters, which are summed as i16
+const KNIGHT_MOVES: std::ops::RangeInclusive<i32> = 2..=8; // on the empty board
+const BISHOP_MOVES: std::ops::RangeInclusive<i32> = 7..=13;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EvalParams {
+    pub pawn_value: i16,
+    pub knight_value: i16,
+    pub bishop_value: i16,
+    pub rook_value: i16,
+    pub queen_value: i16,
+    pub pawn_rows_to_go: [i16; 8], // pawn bonus, indexed by the rows to go to promotion
+    pub pawn_rows_to_go_eg: [i16; 8], // the same for the endgame
+    pub pawn_outer_start: i16,     // a, b, g and h pawns on the start row, so that they move late
+    pub pawn_center_start: i16,    // d and e pawns on the start row, so that they move early
+    pub knight_mobility: i16,      // bonus per knight move from a square ...
+    pub knight_mobility_base: i16, // ... exceeding this number of moves
+    pub bishop_mobility: i16,      // the same for bishop and queen, counting the diagonal squares
+    pub bishop_mobility_base: i16,
+    pub king_corner: i16,          // for the king in a corner of the board
+    pub king_center_eg: i16,       // endgame penalty per row or column distance from the center
+    pub king_moved_penalty: i16,   // castling no longer possible
+    pub rook_moved_penalty: i16,   // castling to that side no longer possible
+    pub doubled_pawn: i16,         // for each additional pawn on a file
+    pub isolated_pawn: i16,        // no own pawns on the adjacent files
+    pub backward_pawn: i16, // can not be supported, and its stop square is attacked by a pawn
+    pub passed_pawn: [i16; 8], // indexed by the row, as seen from the pawn owner
+    pub passed_pawn_eg: [i16; 8], // the same for the endgame
+    pub passed_king_distance: i16, // times king distance difference to the stop square, times row
+    pub king_shield_pawn: i16, // for each missing pawn in front of the king
+    pub king_half_open_file: i16, // no own pawn on the king file or an adjacent file
+    pub king_open_file: i16, // no pawn at all on that file
+    pub king_zone_attack: i16, // for each attacked square next to the opponent king
+    pub extend: Extensions,
+    pub search: SearchSwitches,
+}
+
+// depth extensions of abeta()
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Extensions {
+    pub enabled: bool,       // false to avoid all depth extends
+    pub castling: bool,      // castling moves
+    pub equal_capture: bool, // captures of pieces with similar value
+    pub pawn_march: bool,    // successive pawn moves of a single pawn, to gain conversion to queen
+    pub check: bool,         // when we are in check (or queen is attacked)
+    pub promote: bool,       // pawn promotion
+}
+
+// principal variation search, late move reductions and null move pruning of abeta(),
+// and the aspiration windows of reply()
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchSwitches {
+    pub pvs: bool, // null window searches for all but the first move, with re-search
+    pub lmr: bool, // reduce late quiet moves by one ply, with re-search on fail-high
+    pub lmr_min_depth: u8, // remaining plies needed for a reduction
+    pub lmr_min_moves: u8, // moves searched before the reductions start
+    pub null_move: bool, // skip a turn at interior nodes, cutoff when the reduced search fails high
+    pub null_move_min_depth: u8, // remaining plies needed for a null move
+    pub null_move_reduction: u8, // plies, one more with more than 6 remaining plies
+    pub null_move_verify: bool, // confirm null move cutoffs in the endgame by a reduced search
+    pub aspiration_window: i16, // initial half width around the previous iteration score, 0 for none
+}
+
+impl Default for EvalParams {
+    fn default() -> Self {
+        EvalParams {
+            pawn_value: 100,
+            knight_value: 300,
+            bishop_value: 300,
+            rook_value: 500,
+            queen_value: 900,
+            pawn_rows_to_go: [8, 4, 2, 0, 0, 0, 1, 0], // +1 for pawn at start row, and promote pressure gain
+            pawn_rows_to_go_eg: [0, 24, 16, 10, 6, 3, 0, 0],
+            pawn_outer_start: 2,
+            pawn_center_start: 0,
+            knight_mobility: 4, // range -12..12
+            knight_mobility_base: 5,
+            bishop_mobility: 4, // range -12..12, so exchange of a pawn for very good position may occur
+            bishop_mobility_base: 10,
+            king_corner: -16,
+            king_center_eg: 8,
+            king_moved_penalty: 4,
+            rook_moved_penalty: 2,
+            doubled_pawn: 12,
+            isolated_pawn: 10,
+            backward_pawn: 8,
+            passed_pawn: [0, 5, 10, 15, 25, 40, 60, 0],
+            passed_pawn_eg: [0, 10, 20, 35, 60, 100, 150, 0],
+            passed_king_distance: 1,
+            king_shield_pawn: 10,
+            king_half_open_file: 8,
+            king_open_file: 15,
+            king_zone_attack: 6,
+            extend: Extensions::default(),
+            search: SearchSwitches::default(),
+        }
+    }
+}
+
+impl Default for Extensions {
+    fn default() -> Self {
+        Extensions {
+            enabled: true,
+            castling: true,
+            equal_capture: true,
+            pawn_march: true,
+            check: true,
+            promote: true,
+        }
+    }
+}
+
+impl Default for SearchSwitches {
+    fn default() -> Self {
+        SearchSwitches {
+            pvs: true,
+            lmr: true,
+            lmr_min_depth: 3,
+            lmr_min_moves: 3,
+            null_move: true,
+            null_move_min_depth: 3,
+            null_move_reduction: 2,
+            null_move_verify: true,
+            aspiration_window: 25,
+        }
+    }
+}
+
+impl EvalParams {
+    // indexed by the absolute FigureID
+    pub(super) fn figure_values(&self) -> [i16; KING_ID as usize + 1] {
+        [
+            VOID_VALUE,
+            self.pawn_value,
+            self.knight_value,
+            self.bishop_value,
+            self.rook_value,
+            self.queen_value,
+            KING_VALUE,
+        ]
+    }
+}
+
+// a file name ending with .json is read as JSON, all others as TOML
+pub fn load_params(path: &str) -> Result<EvalParams, EngineError> {
+    let error = |e: &dyn std::fmt::Display| EngineError::InvalidParams(format!("{}: {}", path, e));
+    let text = std::fs::read_to_string(path).map_err(|e| error(&e))?;
+    let params: EvalParams = if path.to_ascii_lowercase().ends_with(".json") {
+        serde_json::from_str(&text).map_err(|e| error(&e))?
+    } else {
+        toml::from_str(&text).map_err(|e| error(&e))?
+    };
+    check_params(&params).map_err(|e| error(&e))?;
+    Ok(params)
+}
+
+// the material and mate scores must not overlap, and the i16 evaluation sums must not overflow
+fn check_params(params: &EvalParams) -> Result<(), String> {
+    let pieces = [
+        params.pawn_value,
+        params.knight_value,
+        params.bishop_value,
+        params.rook_value,
+        params.queen_value,
+    ];
+    if pieces.iter().any(|v| !(1..=MAX_PIECE_VALUE).contains(v)) {
+        return Err(format!("piece values must be in 1..{}", MAX_PIECE_VALUE));
+    }
+    let [pawn, knight, bishop, rook, queen] = pieces.map(|v| v as i32);
+    // the start material of one side, with two pawns promoted to queens
+    let material = 8 * pawn + 2 * (knight + bishop + rook) + queen + 2 * (queen - pawn);
+    if material > MAX_MATERIAL {
+        return Err(format!(
+            "the material of one side with two promotions is {}, more than {}",
+            material, MAX_MATERIAL
+        ));
+    }
+    let scalars = [
+        params.pawn_outer_start,
+        params.pawn_center_start,
+        params.knight_mobility,
+        params.knight_mobility_base,
+        params.bishop_mobility,
+        params.bishop_mobility_base,
+        params.king_corner,
+        params.king_center_eg,
+        params.king_moved_penalty,
+        params.rook_moved_penalty,
+        params.doubled_pawn,
+        params.isolated_pawn,
+        params.backward_pawn,
+        params.passed_king_distance,
+        params.king_shield_pawn,
+        params.king_half_open_file,
+        params.king_open_file,
+        params.king_zone_attack,
+    ];
+    let mut terms = params
+        .pawn_rows_to_go
+        .iter()
+        .chain(&params.pawn_rows_to_go_eg)
+        .chain(&params.passed_pawn)
+        .chain(&params.passed_pawn_eg)
+        .chain(&scalars);
+    if terms.any(|v| !(-MAX_TERM..=MAX_TERM).contains(v)) {
+        return Err(format!(
+            "positional parameters must be in -{}..{}",
+            MAX_TERM, MAX_TERM
+        ));
+    }
+    let mobility_terms = [
+        (
+            "knight",
+            KNIGHT_MOVES,
+            params.knight_mobility_base,
+            params.knight_mobility,
+        ),
+        (
+            "bishop",
+            BISHOP_MOVES,
+            params.bishop_mobility_base,
+            params.bishop_mobility,
+        ),
+    ];
+    for (piece, mut moves, base, bonus) in mobility_terms {
+        if moves.any(|n| mobility(n, base, bonus).abs() > MAX_TERM as i32) {
+            return Err(format!(
+                "{} mobility terms (moves - base) * mobility must be in -{}..{}",
+                piece, MAX_TERM, MAX_TERM
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn mobility(moves: i32, base: i16, bonus: i16) -> i32 {
+    (moves - base as i32) * bonus as i32
+}
+
+// the freedom term of a knight or bishop with the given number of moves on the empty board,
+// clamped for parameters which were not checked by load_params()
+pub(super) fn mobility_term(moves: usize, base: i16, bonus: i16) -> i16 {
+    mobility(moves as i32, base, bonus).clamp(-(MAX_TERM as i32), MAX_TERM as i32) as i16
+}
+
+// the parameters used by evaluation and search, the freedom tables are rebuilt
+pub fn set_params(g: &mut Game, params: EvalParams) {
+    g.figure_value = params.figure_values();
+    g.params = params;
+    init_freedom(g);
+    for el in &mut g.tt {
+        *el = Default::default(); // scores of the old parameters
+    }
+    for el in &mut g.pawn_tt {
+        *el = Default::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::game;
+    use super::*;
+
+    #[test]
+    fn check_params_bounds() {
+        assert_eq!(check_params(&EvalParams::default()), Ok(()));
+        let params = EvalParams {
+            pawn_value: 0,
+            ..Default::default()
+        };
+        assert!(check_params(&params).is_err());
+        // each value is allowed, but the sum would reach the mate scores
+        let params = EvalParams {
+            pawn_value: MAX_PIECE_VALUE,
+            knight_value: MAX_PIECE_VALUE,
+            bishop_value: MAX_PIECE_VALUE,
+            rook_value: MAX_PIECE_VALUE,
+            queen_value: MAX_PIECE_VALUE,
+            ..Default::default()
+        };
+        assert!(check_params(&params).is_err());
+        let mut params = EvalParams::default();
+        params.passed_pawn_eg[6] = 30000;
+        assert!(check_params(&params).is_err());
+        let params = EvalParams {
+            king_corner: -MAX_TERM,
+            ..Default::default()
+        };
+        assert_eq!(check_params(&params), Ok(()));
+        // each term is allowed, but not their product
+        let params = EvalParams {
+            knight_mobility: MAX_TERM,
+            knight_mobility_base: -MAX_TERM,
+            ..Default::default()
+        };
+        assert!(check_params(&params).is_err());
+        let mut g = game(START_FEN);
+        set_params(&mut g, params); // clamped, no overflow
+        assert_eq!(g.freedom[(ARRAY_BASE_6 + W_KNIGHT) as usize][0], MAX_TERM);
+        let params = EvalParams {
+            bishop_mobility: 20,
+            bishop_mobility_base: 3,
+            ..Default::default()
+        };
+        assert_eq!(check_params(&params), Ok(()));
+        assert_eq!(mobility_term(8, -MAX_TERM, MAX_TERM), MAX_TERM);
+    }
+}
@@ -0,0 +1,214 @@
+// Pawn structure evaluation: doubled, isolated, backward and passed pawns.
+// The structure terms depend only on the pawn positions, so they are cached in a small pawn hash table.
+// Passed pawns are stored in the table as well, their king distance bonus is added for each call.
+// Middlegame and endgame scores are interpolated by the game phase.
+
+use super::*;
+
+pub(super) const PAWN_HASH_SIZE: usize = 1 << 14;
+
+const FILE_H: u64 = 0x0101010101010101; // col 0
+
+#[derive(Copy, Clone, Default)]
+pub(super) struct PawnEntry {
+    pawns: [u64; 2], // White, Black -- an empty entry is valid for a board without pawns
+    score: i16,      // for White, middlegame
+    score_eg: i16,   // for White, endgame
+    passed: u64,     // passed pawns of both colors
+}
+
+fn pawn_hash_index(pawns: [u64; 2]) -> usize {
+    let h = pawns[0].wrapping_mul(0x9E3779B97F4A7C15) ^ pawns[1].wrapping_mul(0xC2B2AE3D27D4EB4F);
+    (h >> (64 - PAWN_HASH_SIZE.trailing_zeros())) as usize
+}
+
+fn adjacent_files(c: i8) -> u64 {
+    let mut result = 0;
+    if c > 0 {
+        result |= FILE_H << (c - 1);
+    }
+    if c < 7 {
+        result |= FILE_H << (c + 1);
+    }
+    result
+}
+
+// all squares with a row larger than r
+fn rows_above(r: i8) -> u64 {
+    match r {
+        ..=-1 => !0,
+        0..=6 => !0 << (8 * (r + 1)),
+        _ => 0,
+    }
+}
+
+// all squares with a row smaller than r
+fn rows_below(r: i8) -> u64 {
+    match r {
+        ..=0 => 0,
+        1..=7 => (1 << (8 * r)) - 1,
+        _ => !0,
+    }
+}
+
+fn pawn_structure(params: &EvalParams, pawns: [u64; 2]) -> PawnEntry {
+    let mut result = PawnEntry {
+        pawns,
+        ..Default::default()
+    };
+    for side in 0..2 {
+        let own = pawns[side];
+        let opp = pawns[1 - side];
+        let sign: i16 = if side == 0 { 1 } else { -1 };
+        let mut score: i16 = 0; // common part
+        let mut passed: i16 = 0;
+        let mut passed_eg: i16 = 0;
+        for c in 0..8 {
+            let n = (own & (FILE_H << c)).count_ones() as i16;
+            if n > 1 {
+                score -= params.doubled_pawn * (n - 1);
+            }
+        }
+        let mut bits = own;
+        while bits != 0 {
+            let p = bits.trailing_zeros() as i8;
+            bits &= bits - 1;
+            let (c, r) = (col(p), row(p));
+            let adjacent = adjacent_files(c);
+            let (ahead, behind) = if side == 0 {
+                (rows_above(r), rows_below(r + 1))
+            } else {
+                (rows_below(r), rows_above(r - 1))
+            };
+            if opp & (adjacent | FILE_H << c) & ahead == 0 {
+                let rel_row = if side == 0 { r } else { 7 - r };
+                passed += params.passed_pawn[rel_row as usize];
+                passed_eg += params.passed_pawn_eg[rel_row as usize];
+                result.passed |= 1 << p;
+            } else if own & adjacent == 0 {
+                score -= params.isolated_pawn;
+            } else if own & adjacent & behind == 0 {
+                // no pawn can support it, and its stop square is attacked by an opponent pawn
+                let r2 = r + 2 * sign as i8;
+                if (0..8).contains(&r2) && opp & adjacent & (0xFF << (8 * r2)) != 0 {
+                    score -= params.backward_pawn;
+                }
+            }
+        }
+        result.score += (score + passed) * sign;
+        result.score_eg += (score + passed_eg) * sign;
+    }
+    result
+}
+
+fn distance(a: i8, b: i8) -> i16 {
+    max((row(a) - row(b)).abs(), (col(a) - col(b)).abs()) as i16
+}
+
+// result is for White
+pub(super) fn evaluate_pawns(g: &mut Game, phase: i32) -> i16 {
+    let mut pawns = [0u64; 2];
+    let mut kings = [0i8; 2];
+    for (p, f) in g.board.iter().enumerate() {
+        match *f {
+            W_PAWN => pawns[0] |= 1 << p,
+            B_PAWN => pawns[1] |= 1 << p,
+            W_KING => kings[0] = p as i8,
+            B_KING => kings[1] = p as i8,
+            _ => {}
+        }
+    }
+    let i = pawn_hash_index(pawns);
+    if g.pawn_tt[i].pawns != pawns {
+        g.pawn_tt[i] = pawn_structure(&g.params, pawns);
+    }
+    let entry = g.pawn_tt[i];
+    let mut eg = entry.score_eg as i32;
+    // a passed pawn is more dangerous in the endgame when the own king is near and the opponent king far away
+    let mut bits = entry.passed;
+    while bits != 0 {
+        let p = bits.trailing_zeros() as i8;
+        bits &= bits - 1;
+        let side = (pawns[1] & 1 << p != 0) as usize;
+        let (stop, rel_row) = if side == 0 {
+            (p + 8, row(p))
+        } else {
+            (p - 8, 7 - row(p))
+        };
+        let d = distance(kings[1 - side], stop) - distance(kings[side], stop);
+        let bonus = d * rel_row as i16 * g.params.passed_king_distance;
+        eg += if side == 0 { bonus } else { -bonus } as i32;
+    }
+    taper(entry.score as i32, eg, phase)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::game;
+    use super::*;
+
+    fn structure(g: &mut Game, fen: &str) -> PawnEntry {
+        set_fen(g, fen).unwrap();
+        let mut pawns = [0u64; 2];
+        for (p, f) in g.board.iter().enumerate() {
+            match *f {
+                W_PAWN => pawns[0] |= 1 << p,
+                B_PAWN => pawns[1] |= 1 << p,
+                _ => {}
+            }
+        }
+        pawn_structure(&g.params, pawns)
+    }
+
+    #[test]
+    fn structure_terms() {
+        let mut g = game(START_FEN);
+        let p = g.params.clone();
+        let e = structure(&mut g, START_FEN);
+        assert_eq!((e.score, e.score_eg, e.passed), (0, 0, 0));
+        // the e pawns are doubled, all pawns are isolated
+        let e = structure(&mut g, "4k3/3p1p2/8/8/8/4P3/4P3/4K3 w - - 0 1");
+        assert_eq!(
+            (e.score, e.score_eg, e.passed),
+            (-p.doubled_pawn, -p.doubled_pawn, 0)
+        );
+        // two isolated White pawns against one Black
+        let e = structure(&mut g, "4k3/1p6/8/8/8/8/P1P5/4K3 w - - 0 1");
+        assert_eq!((e.score, e.passed), (-p.isolated_pawn, 0));
+        // d2 is behind e3, and c4 attacks its stop square
+        let e = structure(&mut g, "4k3/8/3p4/4p3/2p5/4P3/3P4/4K3 w - - 0 1");
+        assert_eq!((e.score, e.passed), (-p.backward_pawn, 0));
+        // without c4 it may advance safely, and the Black pawns are isolated
+        let e = structure(&mut g, "4k3/8/3p4/4p3/8/4P3/3P4/4K3 w - - 0 1");
+        assert_eq!(e.score, 0);
+        // a passed pawn on the sixth row, and the same for Black on the third row
+        let e = structure(&mut g, "4k3/8/1P6/8/8/8/8/4K3 w - - 0 1");
+        assert_eq!(
+            (e.score, e.score_eg),
+            (p.passed_pawn[5], p.passed_pawn_eg[5])
+        );
+        assert_eq!(e.passed, 1 << square_from_str(b"b6").unwrap());
+        let e = structure(&mut g, "4k3/8/8/8/8/1p6/8/4K3 b - - 0 1");
+        assert_eq!(
+            (e.score, e.score_eg),
+            (-p.passed_pawn[5], -p.passed_pawn_eg[5])
+        );
+        // a pawn on an adjacent file ahead stops it, and the other way round
+        let e = structure(&mut g, "4k3/2p5/1P6/8/8/8/8/4K3 w - - 0 1");
+        assert_eq!(e.passed, 0);
+    }
+
+    #[test]
+    fn passed_pawn_king_distance() {
+        // the pawn is worth more in the endgame, when its king is near and the other one far away
+        let mut g = game("8/8/1PK5/8/8/8/8/6k1 w - - 0 1");
+        let near = evaluate_pawns(&mut g, 0);
+        set_fen(&mut g, "8/8/1P6/8/8/8/8/2K3k1 w - - 0 1").unwrap();
+        let far = evaluate_pawns(&mut g, 0);
+        assert!(near > far);
+        assert!(far > 0);
+        // the middlegame part ignores the kings
+        set_fen(&mut g, "8/8/1PK5/8/8/8/8/6k1 w - - 0 1").unwrap();
+        assert_eq!(evaluate_pawns(&mut g, PHASE_MAX), g.params.passed_pawn[5]);
+    }
+}
@@ -0,0 +1,151 @@
+// Game records: the start position and the moves of a game, with the engine settings, so that a
+// game can be saved, e.g. as JSON, and continued later with the same state.
+// load_record() rebuilds the game by replaying the moves, which restores the castling rights,
+// the en passant square, the 50-move counter and the repetition history as well.
+
+use super::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameRecord {
+    pub start_fen: String,
+    pub moves: Vec<String>, // in UCI notation
+    pub skill_level: u8,
+    pub contempt: [i16; 2],
+    pub aggression: [i16; 2],
+    pub variant: Variant,
+}
+
+impl Default for GameRecord {
+    fn default() -> Self {
+        GameRecord {
+            start_fen: START_FEN.to_string(),
+            moves: Vec::new(),
+            skill_level: MAX_SKILL_LEVEL,
+            contempt: [0; 2],
+            aggression: [100; 2],
+            variant: Variant::Standard,
+        }
+    }
+}
+
+pub fn game_record(g: &Game) -> GameRecord {
+    GameRecord {
+        start_fen: g.start_fen.clone(),
+        moves: g.played.iter().map(move_to_uci).collect(),
+        skill_level: g.skill_level,
+        contempt: g.contempt,
+        aggression: g.aggression,
+        variant: g.variant,
+    }
+}
+
+// set up the start position of the record and play its moves.
+// The game is unchanged when the record has an invalid position or an illegal move.
+pub fn load_record(g: &mut Game, record: &GameRecord) -> Result<(), EngineError> {
+    if !(1..=MAX_SKILL_LEVEL).contains(&record.skill_level) {
+        return Err(EngineError::InvalidParams(format!(
+            "skill level {} not in 1..={}",
+            record.skill_level, MAX_SKILL_LEVEL
+        )));
+    }
+    // the ranges of the UCI options, larger values overflow the evaluation
+    if record
+        .contempt
+        .iter()
+        .any(|c| !(-MAX_CONTEMPT..=MAX_CONTEMPT).contains(c))
+        || record
+            .aggression
+            .iter()
+            .any(|a| !(0..=MAX_AGGRESSION).contains(a))
+    {
+        return Err(EngineError::InvalidParams(format!(
+            "contempt {:?} not in -{}..={} or aggression {:?} not in 0..={}",
+            record.contempt, MAX_CONTEMPT, MAX_CONTEMPT, record.aggression, MAX_AGGRESSION
+        )));
+    }
+    let old = game_record(g);
+    if let Err(e) = replay(g, record) {
+        let restored = replay(g, &old);
+        debug_assert!(restored.is_ok()); // its moves were legal
+        return Err(e);
+    }
+    g.skill_level = record.skill_level;
+    set_style(g, record.contempt, record.aggression);
+    Ok(())
+}
+
+fn replay(g: &mut Game, record: &GameRecord) -> Result<(), EngineError> {
+    g.variant = record.variant;
+    set_fen(g, &record.start_fen)?;
+    for s in &record.moves {
+        let m = move_from_uci(g, s)?;
+        do_move_promote(g, &m)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::game;
+    use super::*;
+
+    #[test]
+    fn json_round_trip() {
+        let mut g = game("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        for m in ["e1g1", "a8b8", "g1g2", "e8d8"] {
+            let m = move_from_uci(&mut g, m).unwrap();
+            do_move_promote(&mut g, &m).unwrap();
+        }
+        g.skill_level = 7;
+        set_style(&mut g, [20, -30], [150, 50]);
+        let json = serde_json::to_string(&game_record(&g)).unwrap();
+        let record: GameRecord = serde_json::from_str(&json).unwrap();
+        assert_eq!(record, game_record(&g));
+
+        let (board, move_counter, has_moved) = (g.board, g.move_counter, g.has_moved.0);
+        reset_game(&mut g);
+        g.skill_level = MAX_SKILL_LEVEL;
+        set_style(&mut g, [0; 2], [100; 2]);
+        load_record(&mut g, &record).unwrap();
+        assert_eq!(game_record(&g), record);
+        assert_eq!(
+            (g.board, g.move_counter, g.has_moved.0),
+            (board, move_counter, has_moved)
+        );
+    }
+
+    #[test]
+    fn invalid_records_leave_the_game_unchanged() {
+        let mut g = game(START_FEN);
+        let m = move_from_uci(&mut g, "e2e4").unwrap();
+        do_move_promote(&mut g, &m).unwrap();
+        let old = game_record(&g);
+        for record in [
+            GameRecord {
+                moves: vec!["e2e4".to_string(), "e2e4".to_string()],
+                ..Default::default()
+            },
+            GameRecord {
+                start_fen: "8/8/8 w - -".to_string(),
+                ..Default::default()
+            },
+            GameRecord {
+                skill_level: 0,
+                ..Default::default()
+            },
+            GameRecord {
+                aggression: [100, 30000],
+                ..Default::default()
+            },
+            GameRecord {
+                contempt: [-101, 0],
+                ..Default::default()
+            },
+        ] {
+            assert!(load_record(&mut g, &record).is_err());
+            assert_eq!(game_record(&g), old);
+        }
+    }
+}
@@ -0,0 +1,149 @@
+// Static exchange evaluation: the material balance of the capture sequence on a single square,
+// when both sides capture with their least valuable piece and may stop capturing at any time.
+// The attackers are searched again after each capture, so pieces behind a moved slider take part.
+
+use super::*;
+
+fn value(g: &Game, f: i64) -> i32 {
+    g.figure_value[f.unsigned_abs() as usize] as i32
+}
+
+// best becomes a less valuable piece of color with one of ids, which is the first piece on a ray of path from dst
+fn slider_attacker(b: &Board, path: &Path, dst: i8, color: Color, ids: [i64; 2], best: &mut i8) {
+    let mut i = 0;
+    loop {
+        let p = path[dst as usize][i].pos;
+        if p < 0 {
+            break;
+        }
+        let f = b[p as usize];
+        if f == VOID_ID {
+            i += 1;
+        } else {
+            if f * color > 0
+                && ids.contains(&f.abs())
+                && (*best < 0 || f.abs() < b[*best as usize].abs())
+            {
+                *best = p;
+            }
+            i = path[dst as usize][i].nxt_dir_idx as usize;
+        }
+    }
+}
+
+// the position of the least valuable piece of color on board b which attacks dst, or -1
+fn least_valuable_attacker(g: &Game, b: &Board, dst: i8, color: Color) -> i8 {
+    for d in [1, -1] {
+        let p = dst - 8 * color as i8 + d;
+        if (0..64).contains(&p)
+            && (col(p) - col(dst)).abs() == 1
+            && b[p as usize] == PAWN_ID * color
+        {
+            return p;
+        }
+    }
+    let mut best: i8 = -1;
+    for el in g.knight_path[dst as usize]
+        .iter()
+        .take_while(|el| el.pos >= 0)
+    {
+        if b[el.pos as usize] == KNIGHT_ID * color {
+            return el.pos;
+        }
+    }
+    slider_attacker(
+        b,
+        &g.bishop_path,
+        dst,
+        color,
+        [BISHOP_ID, QUEEN_ID],
+        &mut best,
+    );
+    if best >= 0 && b[best as usize].abs() == BISHOP_ID {
+        return best;
+    }
+    slider_attacker(b, &g.rook_path, dst, color, [ROOK_ID, QUEEN_ID], &mut best);
+    if best >= 0 {
+        return best;
+    }
+    for el in g.king_path[dst as usize]
+        .iter()
+        .take_while(|el| el.pos >= 0)
+    {
+        if b[el.pos as usize] == KING_ID * color {
+            return el.pos;
+        }
+    }
+    -1
+}
+
+// the material gain of the capture si -> di for the moving side, 0 for a plain move to an unattacked square
+pub(super) fn see(g: &Game, si: i8, di: i8) -> i16 {
+    let mut b = g.board;
+    let mut gain = [0i32; 33];
+    let mut d = 0;
+    gain[0] = value(g, b[di as usize]);
+    if b[di as usize].abs() == KING_ID {
+        return gain[0] as i16; // the game ends, there is no recapture
+    }
+    let mut attacker = si;
+    let mut color = signum(b[si as usize]);
+    loop {
+        d += 1;
+        // the gain of the opponent, if the attacker is captured in turn
+        gain[d] = value(g, b[attacker as usize]) - gain[d - 1];
+        b[attacker as usize] = VOID_ID;
+        color = -color;
+        attacker = least_valuable_attacker(g, &b, di, color);
+        if attacker < 0 {
+            break;
+        }
+    }
+    while d > 1 {
+        d -= 1;
+        gain[d - 1] = -max(-gain[d - 1], gain[d]);
+    }
+    gain[0] as i16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::game;
+    use super::*;
+
+    fn see_of(fen: &str, m: &str) -> i16 {
+        let g = game(fen);
+        let b = m.as_bytes();
+        see(
+            &g,
+            square_from_str(b).unwrap(),
+            square_from_str(&b[2..]).unwrap(),
+        )
+    }
+
+    #[test]
+    fn exchange_values() {
+        // an undefended pawn
+        assert_eq!(
+            see_of("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1", "e1e5"),
+            100
+        );
+        // a pawn defended by a pawn
+        assert_eq!(see_of("4k3/8/3p4/4p3/8/8/8/4RK2 w - - 0 1", "e1e5"), -400);
+        // the rook behind the first one recaptures
+        assert_eq!(see_of("4k3/8/3p4/4p3/8/8/4R3/4RK2 w - - 0 1", "e2e5"), -300);
+        // the defender does not recapture, as the queen would be lost
+        assert_eq!(see_of("4k3/8/8/3n4/4P3/2N5/8/4K2q w - - 0 1", "e4d5"), 300);
+        assert_eq!(see_of("4k3/8/4p3/3n4/4P3/8/8/4K3 w - - 0 1", "e4d5"), 200);
+        // a plain move to an unattacked square
+        assert_eq!(see_of(START_FEN, "g1f3"), 0);
+    }
+
+    #[test]
+    fn king_capture() {
+        // no exchange, even when the king captures and a queen could recapture
+        let mut g = game("8/8/8/8/8/8/8/3QK2k w - - 0 1");
+        g.board.swap(0, 12); // the black king from h1 to d2
+        assert_eq!(see(&g, 12, 3), KING_VALUE);
+    }
+}
@@ -0,0 +1,107 @@
+// Reduced playing strength for skill levels below MAX_SKILL_LEVEL.
+// The weakened engine searches a few root moves with depth and node caps, adds random noise to their
+// scores and sometimes plays one of the weaker moves on purpose.
+
+use super::*;
+use rand::Rng;
+
+pub const MAX_SKILL_LEVEL: u8 = 20; // full strength
+pub const MIN_ELO: u32 = 800; // the rough playing strength of skill level 1 ...
+pub const MAX_ELO: u32 = 2400; // ... and of MAX_SKILL_LEVEL
+
+const SKILL_LINES: usize = 4; // candidate moves at the root
+const SKILL_NOISE: i64 = 15; // centipawns per level below MAX_SKILL_LEVEL
+
+// for UCI_Elo
+pub fn skill_level_from_elo(elo: u32) -> u8 {
+    let elo = elo.clamp(MIN_ELO, MAX_ELO);
+    (1 + (elo - MIN_ELO) * (MAX_SKILL_LEVEL as u32 - 1) / (MAX_ELO - MIN_ELO)) as u8
+}
+
+// for each candidate move
+fn node_cap(level: i64) -> u64 {
+    ((level + 1).pow(3) * 50) as u64
+}
+
+pub(super) fn weakened_reply(g: &mut Game, limits: &SearchLimits) -> Result<Move, EngineError> {
+    let weakness = (MAX_SKILL_LEVEL - g.skill_level.clamp(1, MAX_SKILL_LEVEL)) as i64;
+    let level = MAX_SKILL_LEVEL as i64 - weakness;
+    let mut l = *limits;
+    let max_depth = (1 + level / 2) as usize;
+    l.max_depth = if l.max_depth > 0 {
+        min(l.max_depth, max_depth)
+    } else {
+        max_depth
+    };
+    let max_nodes = node_cap(level);
+    l.max_nodes = if l.max_nodes > 0 {
+        min(l.max_nodes, max_nodes)
+    } else {
+        max_nodes
+    };
+    if limits.timed() {
+        // the nominal time is shared by the candidate moves
+        l.movetime = allocate_time(g).0 / SKILL_LINES as f32;
+    }
+    let lines = reply_multi_pv(g, &l, SKILL_LINES);
+    if lines.is_empty() {
+        // the search was stopped at once
+        let skill_level = std::mem::replace(&mut g.skill_level, MAX_SKILL_LEVEL);
+        let result = search_move(g, &l);
+        g.skill_level = skill_level;
+        return result;
+    }
+    let mut rng = rand::thread_rng();
+    // never choose a move which gets mated
+    let candidates: Vec<&PvLine> = lines
+        .iter()
+        .filter(|line| line.score > -KING_VALUE_DIV_2 as i64)
+        .collect();
+    let chosen = if candidates.len() > 1 && rng.gen_range(0..1000) < weakness * weakness {
+        candidates[rng.gen_range(0..candidates.len())] // a deliberate mistake
+    } else {
+        lines
+            .iter()
+            .max_by_key(|line| line.score + rng.gen_range(0..=weakness * SKILL_NOISE))
+            .unwrap()
+    };
+    if g.verbose {
+        println!(
+            "skill level {}: playing {} with score {}, best score {}",
+            level,
+            move_to_uci(&chosen.pv[0]),
+            chosen.score,
+            lines[0].score
+        );
+    }
+    let mut result = chosen.pv[0];
+    result.score = chosen.score;
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::game;
+    use super::*;
+
+    #[test]
+    fn node_caps() {
+        let mut g = game("r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 3 3");
+        let mut limits = SearchLimits {
+            max_depth: MAX_DEPTH,
+            ..Default::default()
+        };
+        // the search of each candidate move stops one node after the cap
+        g.skill_level = 10;
+        let r = reply(&mut g, &limits).unwrap();
+        let cap = node_cap(10);
+        assert!(r.stats.nodes > cap);
+        assert!(r.stats.nodes <= SKILL_LINES as u64 * (cap + 1));
+        // a lower node limit of the caller is kept
+        limits.max_nodes = 1000;
+        let params = g.params.clone();
+        set_params(&mut g, params); // clears the transposition table
+        let r = reply(&mut g, &limits).unwrap();
+        assert!(r.stats.nodes <= SKILL_LINES as u64 * 1001);
+    }
+}
@@ -0,0 +1,1323 @@
+// Syzygy endgame tablebases -- probing of the WDL (.rtbw) and DTZ (.rtbz) files
+//
+// The file format and the probing follow Ronald de Man's tbprobe.c, in the
+// form used by Stockfish. Inside this module we use the Stockfish encoding:
+// square a1 is 0 and h8 is 63, white pawn to king are 1 to 6, black ones 9 to 14.
+// The tables are loaded into memory when they are used for the first time.
+
+use super::*;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+const WDL_MAGIC: [u8; 4] = [0x71, 0xE8, 0x23, 0x5D];
+const DTZ_MAGIC: [u8; 4] = [0xD7, 0x66, 0x0C, 0xA5];
+const TB_PIECES: usize = 7;
+
+// flags of the PairsData
+const FLAG_STM: u8 = 1;
+const FLAG_MAPPED: u8 = 2;
+const FLAG_WIN_PLIES: u8 = 4;
+const FLAG_LOSS_PLIES: u8 = 8;
+const FLAG_WIDE: u8 = 16;
+const FLAG_SINGLE_VALUE: u8 = 128;
+
+// win/draw/loss for the side to move. Cursed wins and blessed losses are draws by the 50 move rule.
+const WDL_LOSS: i32 = -2;
+const WDL_BLESSED_LOSS: i32 = -1;
+const WDL_DRAW: i32 = 0;
+const WDL_CURSED_WIN: i32 = 1;
+const WDL_WIN: i32 = 2;
+
+// score of a tablebase win, less than SURE_CHECKMATE, but more than any material advantage
+const TB_WIN_SCORE: i64 = 5000;
+
+#[derive(Copy, Clone, PartialEq)]
+enum ProbeState {
+    Fail, // a table is missing
+    Ok,
+    ChangeStm,       // the DTZ table stores the other side to move
+    ZeroingBestMove, // the best move is a capture or pawn move
+}
+
+// ### tables for the encoding of positions to table indices
+
+struct Maps {
+    b1h1h7: [u64; 64],        // squares below the a1-h8 diagonal to 0..27
+    a1d1d4: [u64; 64],        // squares of the a1-d1-d4 triangle to 0..9
+    kk: [[u64; 64]; 10],      // the 462 legal positions of two kings
+    binomial: [[u64; 64]; 6], // binomial[k][n]: ways to choose k of n
+    pawns: [usize; 64],       // squares a2-h7 to 0..47, the highest is the leading pawn
+    lead_pawn_idx: [[u64; 64]; 6],
+    lead_pawns_size: [[u64; 4]; 6],
+}
+
+fn file_of(s: usize) -> usize {
+    s & 7
+}
+
+fn rank_of(s: usize) -> usize {
+    s >> 3
+}
+
+fn off_a1h8(s: usize) -> i32 {
+    rank_of(s) as i32 - file_of(s) as i32
+}
+
+impl Maps {
+    fn new() -> Maps {
+        let mut m = Maps {
+            b1h1h7: [0; 64],
+            a1d1d4: [0; 64],
+            kk: [[0; 64]; 10],
+            binomial: [[0; 64]; 6],
+            pawns: [0; 64],
+            lead_pawn_idx: [[0; 64]; 6],
+            lead_pawns_size: [[0; 4]; 6],
+        };
+        let mut code = 0;
+        for s in 0..64 {
+            if off_a1h8(s) < 0 {
+                m.b1h1h7[s] = code;
+                code += 1;
+            }
+        }
+        let mut diagonal = Vec::new();
+        code = 0;
+        for s in [0, 1, 2, 3, 8, 9, 10, 11, 16, 17, 18, 19, 24, 25, 26, 27] {
+            if off_a1h8(s) < 0 {
+                m.a1d1d4[s] = code;
+                code += 1;
+            } else if off_a1h8(s) == 0 {
+                diagonal.push(s);
+            }
+        }
+        // diagonal squares are encoded as last ones
+        for s in diagonal {
+            m.a1d1d4[s] = code;
+            code += 1;
+        }
+        // if the first king is on the a1-d4 diagonal, the other one shall not be above the a1-h8 diagonal
+        let mut both_on_diagonal = Vec::new();
+        code = 0;
+        for idx in 0..10 {
+            for s1 in 0..28 {
+                if m.a1d1d4[s1] != idx as u64 || (idx == 0 && s1 != 1) {
+                    continue; // b1 is mapped to 0
+                }
+                for s2 in 0..64 {
+                    if file_of(s1).abs_diff(file_of(s2)) <= 1
+                        && rank_of(s1).abs_diff(rank_of(s2)) <= 1
+                    {
+                        continue; // illegal position
+                    } else if off_a1h8(s1) == 0 && off_a1h8(s2) > 0 {
+                        continue; // first on diagonal, second above
+                    } else if off_a1h8(s1) == 0 && off_a1h8(s2) == 0 {
+                        both_on_diagonal.push((idx, s2));
+                    } else {
+                        m.kk[idx][s2] = code;
+                        code += 1;
+                    }
+                }
+            }
+        }
+        for (idx, s2) in both_on_diagonal {
+            m.kk[idx][s2] = code;
+            code += 1;
+        }
+        m.binomial[0][0] = 1;
+        for n in 1..64 {
+            for k in 0..6.min(n + 1) {
+                m.binomial[k][n] = if k > 0 { m.binomial[k - 1][n - 1] } else { 0 }
+                    + if k < n { m.binomial[k][n - 1] } else { 0 };
+            }
+        }
+        // the leading pawn is the one nearest the edge and, for the same file, with the lowest rank
+        let mut available_squares = 48;
+        for lead_pawns_cnt in 1..=5 {
+            for f in 0..4 {
+                let mut idx = 0;
+                for r in 1..7 {
+                    let s = 8 * r + f;
+                    if lead_pawns_cnt == 1 {
+                        available_squares -= 2;
+                        m.pawns[s] = available_squares + 1;
+                        m.pawns[s ^ 7] = available_squares;
+                    }
+                    m.lead_pawn_idx[lead_pawns_cnt][s] = idx;
+                    idx += m.binomial[lead_pawns_cnt - 1][m.pawns[s]];
+                }
+                m.lead_pawns_size[lead_pawns_cnt][f] = idx;
+            }
+        }
+        m
+    }
+}
+
+// ### a minimal board with legal move generation, used for the probing only
+
+#[derive(Copy, Clone)]
+struct Pos {
+    board: [u8; 64],
+    stm: u8,           // 0 for White, 1 for Black, as the color bit of the pieces
+    ep: Option<usize>, // en passant target square
+}
+
+type TbMove = (usize, usize, u8); // from, to, promotion piece
+
+const KNIGHT_STEPS: [(i32, i32); 8] = [
+    (1, 2),
+    (2, 1),
+    (2, -1),
+    (1, -2),
+    (-1, -2),
+    (-2, -1),
+    (-2, 1),
+    (-1, 2),
+];
+const KING_STEPS: [(i32, i32); 8] = [
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+];
+const ROOK_DIRS: [(i32, i32); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+const BISHOP_DIRS: [(i32, i32); 4] = [(1, 1), (-1, 1), (-1, -1), (1, -1)];
+
+fn step(s: usize, df: i32, dr: i32) -> Option<usize> {
+    let f = file_of(s) as i32 + df;
+    let r = rank_of(s) as i32 + dr;
+    if (0..8).contains(&f) && (0..8).contains(&r) {
+        Some((r * 8 + f) as usize)
+    } else {
+        None
+    }
+}
+
+impl Pos {
+    fn piece_count(&self) -> usize {
+        self.board.iter().filter(|&&p| p != 0).count()
+    }
+
+    fn color_of(p: u8) -> u8 {
+        p >> 3
+    }
+
+    // is square s attacked by a piece of color c
+    fn attacked(&self, s: usize, c: u8) -> bool {
+        let piece = |t: u8| c * 8 + t;
+        let pawn_dir = if c == 0 { -1 } else { 1 };
+        for df in [-1, 1] {
+            if step(s, df, pawn_dir).is_some_and(|a| self.board[a] == piece(1)) {
+                return true;
+            }
+        }
+        for (df, dr) in KNIGHT_STEPS {
+            if step(s, df, dr).is_some_and(|a| self.board[a] == piece(2)) {
+                return true;
+            }
+        }
+        for (df, dr) in KING_STEPS {
+            if step(s, df, dr).is_some_and(|a| self.board[a] == piece(6)) {
+                return true;
+            }
+        }
+        for (dirs, slider) in [(ROOK_DIRS, 4), (BISHOP_DIRS, 3)] {
+            for (df, dr) in dirs {
+                let mut a = s;
+                while let Some(n) = step(a, df, dr) {
+                    a = n;
+                    let p = self.board[a];
+                    if p != 0 {
+                        if p == piece(slider) || p == piece(5) {
+                            return true;
+                        }
+                        break;
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    fn king_square(&self, c: u8) -> usize {
+        self.board.iter().position(|&p| p == c * 8 + 6).unwrap()
+    }
+
+    fn in_check(&self) -> bool {
+        self.attacked(self.king_square(self.stm), 1 - self.stm)
+    }
+
+    fn is_capture(&self, m: TbMove) -> bool {
+        self.board[m.1] != 0 || (self.board[m.0] & 7 == 1 && Some(m.1) == self.ep)
+    }
+
+    fn is_zeroing(&self, m: TbMove) -> bool {
+        self.is_capture(m) || self.board[m.0] & 7 == 1
+    }
+
+    fn do_move(&self, m: TbMove) -> Pos {
+        let mut n = *self;
+        let (from, to, promotion) = m;
+        let p = self.board[from];
+        n.ep = None;
+        if p & 7 == 1 {
+            if Some(to) == self.ep {
+                n.board[8 * rank_of(from) + file_of(to)] = 0;
+            }
+            if from.abs_diff(to) == 16 {
+                n.ep = Some((from + to) / 2);
+            }
+        }
+        n.board[to] = if promotion != 0 { promotion } else { p };
+        n.board[from] = 0;
+        n.stm = 1 - self.stm;
+        n
+    }
+
+    fn legal_moves(&self) -> Vec<TbMove> {
+        let mut moves: Vec<TbMove> = Vec::new();
+        let c = self.stm;
+        let own = |p: u8| p != 0 && Pos::color_of(p) == c;
+        let mut add = |from: usize, to: usize| moves.push((from, to, 0));
+        let mut pawn_moves: Vec<(usize, usize)> = Vec::new();
+        for s in 0..64 {
+            let p = self.board[s];
+            if !own(p) {
+                continue;
+            }
+            match p & 7 {
+                1 => {
+                    let dir = if c == 0 { 1 } else { -1 };
+                    if let Some(t) = step(s, 0, dir) {
+                        if self.board[t] == 0 {
+                            pawn_moves.push((s, t));
+                            let start_rank = if c == 0 { 1 } else { 6 };
+                            if rank_of(s) == start_rank {
+                                let t2 = step(t, 0, dir).unwrap();
+                                if self.board[t2] == 0 {
+                                    pawn_moves.push((s, t2));
+                                }
+                            }
+                        }
+                    }
+                    for df in [-1, 1] {
+                        if let Some(t) = step(s, df, dir) {
+                            let q = self.board[t];
+                            if (q != 0 && Pos::color_of(q) != c) || Some(t) == self.ep {
+                                pawn_moves.push((s, t));
+                            }
+                        }
+                    }
+                }
+                2 | 6 => {
+                    let steps = if p & 7 == 2 { KNIGHT_STEPS } else { KING_STEPS };
+                    for (df, dr) in steps {
+                        if let Some(t) = step(s, df, dr) {
+                            if !own(self.board[t]) {
+                                add(s, t);
+                            }
+                        }
+                    }
+                }
+                t => {
+                    let mut dirs: Vec<(i32, i32)> = Vec::new();
+                    if t == 4 || t == 5 {
+                        dirs.extend(ROOK_DIRS);
+                    }
+                    if t == 3 || t == 5 {
+                        dirs.extend(BISHOP_DIRS);
+                    }
+                    for (df, dr) in dirs {
+                        let mut a = s;
+                        while let Some(n) = step(a, df, dr) {
+                            a = n;
+                            if own(self.board[a]) {
+                                break;
+                            }
+                            add(s, a);
+                            if self.board[a] != 0 {
+                                break;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        for (from, to) in pawn_moves {
+            if rank_of(to) == 0 || rank_of(to) == 7 {
+                for t in [5, 4, 3, 2] {
+                    moves.push((from, to, c * 8 + t));
+                }
+            } else {
+                moves.push((from, to, 0));
+            }
+        }
+        moves.retain(|&m| {
+            let n = self.do_move(m);
+            !n.attacked(n.king_square(c), 1 - c)
+        });
+        moves
+    }
+
+    // like "KRPvKR", the pieces of color c first
+    fn material(&self, c: u8) -> String {
+        let mut s = String::new();
+        for side in [c, 1 - c] {
+            for (t, ch) in [(6, 'K'), (5, 'Q'), (4, 'R'), (3, 'B'), (2, 'N'), (1, 'P')] {
+                for _ in 0..self.board.iter().filter(|&&p| p == side * 8 + t).count() {
+                    s.push(ch);
+                }
+            }
+            if side == c {
+                s.push('v');
+            }
+        }
+        s
+    }
+}
+
+// ### the table files
+
+fn read_u16(data: &[u8], p: usize) -> u16 {
+    u16::from_le_bytes([data[p], data[p + 1]])
+}
+
+fn read_u32(data: &[u8], p: usize) -> u32 {
+    u32::from_le_bytes(data[p..p + 4].try_into().unwrap())
+}
+
+// the compressed data is big endian, and we may read a few bytes past the end of the last block
+fn read_u32_be(data: &[u8], p: usize) -> u32 {
+    let mut b = [0; 4];
+    for (i, x) in b.iter_mut().enumerate() {
+        *x = data.get(p + i).copied().unwrap_or(0);
+    }
+    u32::from_be_bytes(b)
+}
+
+#[derive(Clone, Default)]
+struct PairsData {
+    flags: u8,
+    size_of_block: u64,
+    span: u64,
+    num_blocks: u64,
+    min_sym_len: u8,
+    lowest_sym: usize, // offsets into the table data
+    btree: usize,
+    block_length: usize,
+    block_length_size: u64,
+    sparse_index: usize,
+    sparse_index_size: u64,
+    data: usize,
+    base64: Vec<u64>, // base64[l - min_sym_len] is the 64 bit padded lowest symbol of length l
+    symlen: Vec<u8>,  // number of values (-1) represented by a given Huffman symbol
+    pieces: [u8; TB_PIECES],
+    group_idx: [u64; TB_PIECES + 1],
+    group_len: [usize; TB_PIECES + 1],
+    map_idx: [u16; 4], // for DTZ: win, loss, cursed win, blessed loss
+}
+
+struct Table {
+    data: Vec<u8>,
+    dtz: bool,
+    symmetric: bool, // same pieces for both colors
+    has_pawns: bool,
+    has_unique_pieces: bool,
+    pawn_count: [usize; 2], // leading color first
+    piece_count: usize,
+    items: [Vec<PairsData>; 2], // for each side to move and leading pawn file
+    map: usize,
+}
+
+// the left and right symbols of the recursive pairing
+fn btree_lr(data: &[u8], d: &PairsData, sym: usize) -> (usize, usize) {
+    let b = &data[d.btree + 3 * sym..d.btree + 3 * sym + 3];
+    (
+        ((b[1] as usize & 0xF) << 8) | b[0] as usize,
+        ((b[2] as usize) << 4) | (b[1] as usize >> 4),
+    )
+}
+
+fn set_symlen(data: &[u8], d: &mut PairsData, s: usize, visited: &mut [bool]) -> u8 {
+    visited[s] = true;
+    let (sl, sr) = btree_lr(data, d, s);
+    if sr == 0xFFF {
+        return 0;
+    }
+    if !visited[sl] {
+        d.symlen[sl] = set_symlen(data, d, sl, visited);
+    }
+    if !visited[sr] {
+        d.symlen[sr] = set_symlen(data, d, sr, visited);
+    }
+    d.symlen[sl].wrapping_add(d.symlen[sr]).wrapping_add(1)
+}
+
+fn set_sizes(data: &[u8], d: &mut PairsData, mut p: usize) -> usize {
+    d.flags = data[p];
+    p += 1;
+    if d.flags & FLAG_SINGLE_VALUE != 0 {
+        d.min_sym_len = data[p]; // here we store the single value
+        return p + 1;
+    }
+    let groups = d.group_len.iter().position(|&l| l == 0).unwrap();
+    let tb_size = d.group_idx[groups];
+    d.size_of_block = 1 << data[p];
+    d.span = 1 << data[p + 1];
+    d.sparse_index_size = tb_size.div_ceil(d.span);
+    let padding = data[p + 2] as u64;
+    d.num_blocks = read_u32(data, p + 3) as u64;
+    d.block_length_size = d.num_blocks + padding; // so that the sparse index does not point out of range
+    let max_sym_len = data[p + 7];
+    d.min_sym_len = data[p + 8];
+    p += 9;
+    d.lowest_sym = p;
+    let n = (max_sym_len - d.min_sym_len + 1) as usize;
+    // canonical Huffman code, longer symbols have lower numeric values
+    d.base64 = vec![0; n];
+    for i in (0..n - 1).rev() {
+        d.base64[i] = d.base64[i + 1]
+            .wrapping_add(read_u16(data, p + 2 * i) as u64)
+            .wrapping_sub(read_u16(data, p + 2 * i + 2) as u64)
+            / 2;
+    }
+    for i in 0..n {
+        d.base64[i] = d.base64[i]
+            .checked_shl(64 - i as u32 - d.min_sym_len as u32)
+            .unwrap_or(0);
+    }
+    p += 2 * n;
+    let sym_count = read_u16(data, p) as usize;
+    p += 2;
+    d.btree = p;
+    d.symlen = vec![0; sym_count];
+    let mut visited = vec![false; sym_count];
+    for sym in 0..sym_count {
+        if !visited[sym] {
+            d.symlen[sym] = set_symlen(data, d, sym, &mut visited);
+        }
+    }
+    p + 3 * sym_count + (sym_count & 1)
+}
+
+fn decompress_pairs(data: &[u8], d: &PairsData, idx: u64) -> i32 {
+    if d.flags & FLAG_SINGLE_VALUE != 0 {
+        return d.min_sym_len as i32;
+    }
+    // the sparse index entry k stores block and offset of the value with index k * span + span / 2
+    let k = (idx / d.span) as usize;
+    let mut block = read_u32(data, d.sparse_index + 6 * k) as usize;
+    let mut offset = read_u16(data, d.sparse_index + 6 * k + 4) as i64;
+    offset += (idx % d.span) as i64 - (d.span / 2) as i64;
+    let block_length = |b: usize| read_u16(data, d.block_length + 2 * b) as i64;
+    while offset < 0 {
+        block -= 1;
+        offset += block_length(block) + 1;
+    }
+    while offset > block_length(block) {
+        offset -= block_length(block) + 1;
+        block += 1;
+    }
+    let mut ptr = d.data + block * d.size_of_block as usize;
+    let mut buf64 = ((read_u32_be(data, ptr) as u64) << 32) | read_u32_be(data, ptr + 4) as u64;
+    ptr += 8;
+    let mut buf64_size = 64;
+    let mut sym;
+    loop {
+        let mut len = 0;
+        while buf64 < d.base64[len] {
+            len += 1;
+        }
+        sym = ((buf64 - d.base64[len]) >> (64 - len - d.min_sym_len as usize)) as usize;
+        sym += read_u16(data, d.lowest_sym + 2 * len) as usize;
+        if offset < d.symlen[sym] as i64 + 1 {
+            break;
+        }
+        offset -= d.symlen[sym] as i64 + 1;
+        len += d.min_sym_len as usize;
+        buf64 <<= len;
+        buf64_size -= len;
+        if buf64_size <= 32 {
+            buf64_size += 32;
+            buf64 |= (read_u32_be(data, ptr) as u64) << (64 - buf64_size);
+            ptr += 4;
+        }
+    }
+    // expand the symbol recursively, until we reach a leaf
+    while d.symlen[sym] != 0 {
+        let (left, right) = btree_lr(data, d, sym);
+        if offset < d.symlen[left] as i64 + 1 {
+            sym = left;
+        } else {
+            offset -= d.symlen[left] as i64 + 1;
+            sym = right;
+        }
+    }
+    btree_lr(data, d, sym).0 as i32
+}
+
+impl Table {
+    // name like "KRPvKR", White has the pieces before the v
+    fn new(data: Vec<u8>, name: &str, dtz: bool, maps: &Maps) -> Option<Table> {
+        let magic = if dtz { DTZ_MAGIC } else { WDL_MAGIC };
+        if data.len() < 5 || data[0..4] != magic {
+            return None;
+        }
+        let (white, black) = name.split_once('v')?;
+        let count = |s: &str, c: char| s.chars().filter(|&x| x == c).count();
+        let (wp, bp) = (count(white, 'P'), count(black, 'P'));
+        // the leading color is the one with less pawns, but with at least one
+        let white_leads = bp == 0 || (wp > 0 && bp >= wp);
+        let mut t = Table {
+            data,
+            dtz,
+            symmetric: white == black,
+            has_pawns: wp + bp > 0,
+            has_unique_pieces: "PNBRQ"
+                .chars()
+                .any(|c| count(white, c) == 1 || count(black, c) == 1),
+            pawn_count: if white_leads { [wp, bp] } else { [bp, wp] },
+            piece_count: white.len() + black.len(),
+            items: [Vec::new(), Vec::new()],
+            map: 0,
+        };
+        if t.piece_count > TB_PIECES || (t.data[4] & 2 != 0) != t.has_pawns {
+            return None;
+        }
+        let sides = if !dtz && !t.symmetric { 2 } else { 1 };
+        let files = if t.has_pawns { 4 } else { 1 };
+        let pp = t.has_pawns && t.pawn_count[1] > 0; // pawns on both sides
+        let data = std::mem::take(&mut t.data);
+        let mut p = 5;
+        for i in 0..sides {
+            t.items[i] = vec![PairsData::default(); files];
+        }
+        for f in 0..files {
+            let order = [
+                [data[p] & 0xF, if pp { data[p + 1] & 0xF } else { 0xF }],
+                [data[p] >> 4, if pp { data[p + 1] >> 4 } else { 0xF }],
+            ];
+            p += 1 + pp as usize;
+            for k in 0..t.piece_count {
+                for i in 0..sides {
+                    t.items[i][f].pieces[k] = if i == 1 { data[p] >> 4 } else { data[p] & 0xF };
+                }
+                p += 1;
+            }
+            for (i, &o) in order.iter().enumerate().take(sides) {
+                let mut d = std::mem::take(&mut t.items[i][f]);
+                t.set_groups(&mut d, o, f, maps);
+                t.items[i][f] = d;
+            }
+        }
+        p += p & 1;
+        for f in 0..files {
+            for i in 0..sides {
+                p = set_sizes(&data, &mut t.items[i][f], p);
+            }
+        }
+        if dtz {
+            t.map = p;
+            for f in 0..files {
+                let d = &mut t.items[0][f];
+                if d.flags & FLAG_MAPPED != 0 {
+                    if d.flags & FLAG_WIDE != 0 {
+                        p += p & 1;
+                        for i in 0..4 {
+                            d.map_idx[i] = ((p - t.map) / 2 + 1) as u16;
+                            p += 2 * read_u16(&data, p) as usize + 2;
+                        }
+                    } else {
+                        for i in 0..4 {
+                            d.map_idx[i] = (p - t.map + 1) as u16;
+                            p += data[p] as usize + 1;
+                        }
+                    }
+                }
+            }
+            p += p & 1;
+        }
+        for f in 0..files {
+            for i in 0..sides {
+                t.items[i][f].sparse_index = p;
+                p += 6 * t.items[i][f].sparse_index_size as usize;
+            }
+        }
+        for f in 0..files {
+            for i in 0..sides {
+                t.items[i][f].block_length = p;
+                p += 2 * t.items[i][f].block_length_size as usize;
+            }
+        }
+        for f in 0..files {
+            for i in 0..sides {
+                p = (p + 0x3F) & !0x3F; // 64 byte alignment
+                t.items[i][f].data = p;
+                p += (t.items[i][f].num_blocks * t.items[i][f].size_of_block) as usize;
+            }
+        }
+        if p > data.len() {
+            return None; // truncated file
+        }
+        t.data = data;
+        Some(t)
+    }
+
+    // the pieces of a group, e.g. two rooks, are encoded together. The order of the groups
+    // in the index is given by order[], the first group is the one of the leading pawns or pieces.
+    fn set_groups(&self, d: &mut PairsData, order: [u8; 2], f: usize, maps: &Maps) {
+        let mut n = 0;
+        let mut first_len: i32 = if self.has_pawns {
+            0
+        } else if self.has_unique_pieces {
+            3
+        } else {
+            2
+        };
+        d.group_len[n] = 1;
+        for i in 1..self.piece_count {
+            first_len -= 1;
+            if first_len > 0 || d.pieces[i] == d.pieces[i - 1] {
+                d.group_len[n] += 1;
+            } else {
+                n += 1;
+                d.group_len[n] = 1;
+            }
+        }
+        n += 1;
+        d.group_len[n] = 0;
+        let pp = self.has_pawns && self.pawn_count[1] > 0;
+        let mut next = if pp { 2 } else { 1 };
+        let mut free_squares = 64 - d.group_len[0] - if pp { d.group_len[1] } else { 0 };
+        let mut idx: u64 = 1;
+        let mut k = 0;
+        while next < n || k == order[0] as usize || k == order[1] as usize {
+            if k == order[0] as usize {
+                d.group_idx[0] = idx;
+                idx *= if self.has_pawns {
+                    maps.lead_pawns_size[d.group_len[0]][f]
+                } else if self.has_unique_pieces {
+                    31332
+                } else {
+                    462
+                };
+            } else if k == order[1] as usize {
+                d.group_idx[1] = idx;
+                idx *= maps.binomial[d.group_len[1]][48 - d.group_len[0]];
+            } else {
+                d.group_idx[next] = idx;
+                idx *= maps.binomial[d.group_len[next]][free_squares];
+                free_squares -= d.group_len[next];
+                next += 1;
+            }
+            k += 1;
+        }
+        d.group_idx[n] = idx;
+    }
+
+    // DTZ values are stored as moves or plies, and may be mapped
+    fn map_score(&self, f: usize, value: i32, wdl: i32) -> i32 {
+        if !self.dtz {
+            return value - 2;
+        }
+        const WDL_MAP: [usize; 5] = [1, 3, 0, 2, 0];
+        let d = &self.items[0][f];
+        let mut value = value;
+        if d.flags & FLAG_MAPPED != 0 {
+            let i = d.map_idx[WDL_MAP[(wdl + 2) as usize]] as usize + value as usize;
+            value = if d.flags & FLAG_WIDE != 0 {
+                read_u16(&self.data, self.map + 2 * i) as i32
+            } else {
+                self.data[self.map + i] as i32
+            };
+        }
+        if (wdl == WDL_WIN && d.flags & FLAG_WIN_PLIES == 0)
+            || (wdl == WDL_LOSS && d.flags & FLAG_LOSS_PLIES == 0)
+            || wdl == WDL_CURSED_WIN
+            || wdl == WDL_BLESSED_LOSS
+        {
+            value *= 2;
+        }
+        value + 1
+    }
+
+    // black_stronger: the position has the material of the table with the colors swapped
+    fn probe(&self, maps: &Maps, pos: &Pos, black_stronger: bool, wdl: i32) -> Option<i32> {
+        let flip = (pos.stm == 1 && self.symmetric) || black_stronger;
+        let flip_color = if flip { 8 } else { 0 };
+        let flip_squares = if flip { 56 } else { 0 };
+        let stm = (flip as u8 ^ pos.stm) as usize;
+        let mut squares = [0usize; TB_PIECES];
+        let mut pieces = [0u8; TB_PIECES];
+        let mut size = 0;
+        let mut lead_pawns_cnt = 0;
+        let mut lead_pawns = [false; 64];
+        let mut tb_file = 0;
+        if self.has_pawns {
+            // the pawns of the leading color come first in all the 4 file tables
+            let pc = self.items[0][0].pieces[0] ^ flip_color;
+            for (s, &p) in pos.board.iter().enumerate() {
+                if p == pc {
+                    lead_pawns[s] = true;
+                    squares[size] = s ^ flip_squares;
+                    size += 1;
+                }
+            }
+            lead_pawns_cnt = size;
+            let lead = (0..size).max_by_key(|&i| maps.pawns[squares[i]]).unwrap();
+            squares.swap(0, lead);
+            tb_file = file_of(squares[0]).min(7 - file_of(squares[0]));
+        }
+        // DTZ tables store only one side to move
+        let one_sided = !self.symmetric || self.has_pawns;
+        if self.dtz && (self.items[0][tb_file].flags & FLAG_STM) as usize != stm && one_sided {
+            return None;
+        }
+        for (s, &p) in pos.board.iter().enumerate() {
+            if p != 0 && !lead_pawns[s] {
+                squares[size] = s ^ flip_squares;
+                pieces[size] = p ^ flip_color;
+                size += 1;
+            }
+        }
+        let side = if self.items[1].is_empty() { 0 } else { stm };
+        let d = &self.items[side][tb_file];
+        // the same sequence of pieces as in the table
+        for i in lead_pawns_cnt..size - 1 {
+            for j in i + 1..size {
+                if d.pieces[i] == pieces[j] {
+                    pieces.swap(i, j);
+                    squares.swap(i, j);
+                    break;
+                }
+            }
+        }
+        // the leading piece shall be in the a1-d1-d4 triangle
+        if file_of(squares[0]) > 3 {
+            for s in squares.iter_mut().take(size) {
+                *s ^= 7;
+            }
+        }
+        let mut idx: u64;
+        if self.has_pawns {
+            idx = maps.lead_pawn_idx[lead_pawns_cnt][squares[0]];
+            squares[1..lead_pawns_cnt].sort_by_key(|&s| maps.pawns[s]);
+            for (i, &s) in squares.iter().enumerate().take(lead_pawns_cnt).skip(1) {
+                idx += maps.binomial[i][maps.pawns[s]];
+            }
+        } else {
+            if rank_of(squares[0]) > 3 {
+                for s in squares.iter_mut().take(size) {
+                    *s ^= 56;
+                }
+            }
+            // the first piece of the leading group not on the a1-h8 diagonal shall be below it
+            for i in 0..d.group_len[0] {
+                if off_a1h8(squares[i]) == 0 {
+                    continue;
+                }
+                if off_a1h8(squares[i]) > 0 {
+                    for s in squares.iter_mut().take(size).skip(i) {
+                        *s = ((*s >> 3) | (*s << 3)) & 63;
+                    }
+                }
+                break;
+            }
+            if self.has_unique_pieces {
+                let s = squares;
+                let adjust1 = (s[1] > s[0]) as u64;
+                let adjust2 = (s[2] > s[0]) as u64 + (s[2] > s[1]) as u64;
+                let r = |i: usize| rank_of(s[i]) as u64;
+                idx = if off_a1h8(s[0]) != 0 {
+                    (maps.a1d1d4[s[0]] * 63 + (s[1] as u64 - adjust1)) * 62 + s[2] as u64 - adjust2
+                } else if off_a1h8(s[1]) != 0 {
+                    (6 * 63 + r(0) * 28 + maps.b1h1h7[s[1]]) * 62 + s[2] as u64 - adjust2
+                } else if off_a1h8(s[2]) != 0 {
+                    6 * 63 * 62
+                        + 4 * 28 * 62
+                        + r(0) * 7 * 28
+                        + (r(1) - adjust1) * 28
+                        + maps.b1h1h7[s[2]]
+                } else {
+                    6 * 63 * 62
+                        + 4 * 28 * 62
+                        + 4 * 7 * 28
+                        + r(0) * 7 * 6
+                        + (r(1) - adjust1) * 6
+                        + (r(2) - adjust2)
+                };
+            } else {
+                idx = maps.kk[maps.a1d1d4[squares[0]] as usize][squares[1]];
+            }
+        }
+        idx *= d.group_idx[0];
+        // the remaining groups, each in ascending order of the squares
+        let mut start = d.group_len[0];
+        let mut remaining_pawns = self.has_pawns && self.pawn_count[1] > 0;
+        let mut next = 1;
+        while d.group_len[next] != 0 {
+            let len = d.group_len[next];
+            squares[start..start + len].sort();
+            let mut n: u64 = 0;
+            for i in 0..len {
+                let s = squares[start + i];
+                let adjust = squares[..start].iter().filter(|&&x| s > x).count();
+                n += maps.binomial[i + 1][s - adjust - 8 * remaining_pawns as usize];
+            }
+            remaining_pawns = false;
+            idx += n * d.group_idx[next];
+            start += len;
+            next += 1;
+        }
+        Some(self.map_score(tb_file, decompress_pairs(&self.data, d, idx), wdl))
+    }
+}
+
+pub struct Tablebases {
+    dir: PathBuf,
+    max_pieces: usize,
+    maps: Maps,
+    tables: HashMap<(String, bool), Option<Table>>, // (name, dtz)
+}
+
+impl Tablebases {
+    // a directory with the .rtbw and .rtbz files
+    pub fn open(dir: &str) -> std::io::Result<Tablebases> {
+        let mut max_pieces = 0;
+        for entry in std::fs::read_dir(dir)? {
+            let name = entry?.file_name().to_string_lossy().to_string();
+            if let Some(stem) = name.strip_suffix(".rtbw") {
+                max_pieces = max_pieces.max(stem.len() - 1);
+            }
+        }
+        if max_pieces == 0 {
+            return Err(std::io::Error::other(format!(
+                "no Syzygy tables in {}",
+                dir
+            )));
+        }
+        Ok(Tablebases {
+            dir: PathBuf::from(dir),
+            max_pieces: max_pieces.min(TB_PIECES),
+            maps: Maps::new(),
+            tables: HashMap::new(),
+        })
+    }
+
+    pub fn max_pieces(&self) -> usize {
+        self.max_pieces
+    }
+
+    fn load(&mut self, name: &str, dtz: bool) -> bool {
+        let key = (name.to_string(), dtz);
+        if !self.tables.contains_key(&key) {
+            let path = self
+                .dir
+                .join(format!("{}.{}", name, if dtz { "rtbz" } else { "rtbw" }));
+            let table = std::fs::read(path)
+                .ok()
+                .and_then(|data| Table::new(data, name, dtz, &self.maps));
+            self.tables.insert(key.clone(), table);
+        }
+        self.tables[&key].is_some()
+    }
+
+    fn probe_table(&mut self, pos: &Pos, dtz: bool, wdl: i32, state: &mut ProbeState) -> i32 {
+        if pos.piece_count() == 2 {
+            return WDL_DRAW; // KvK
+        }
+        let name = pos.material(0);
+        let (name, black_stronger) = if self.load(&name, dtz) {
+            (name, false)
+        } else {
+            let swapped = pos.material(1);
+            if !self.load(&swapped, dtz) {
+                *state = ProbeState::Fail;
+                return 0;
+            }
+            (swapped, true)
+        };
+        let table = self.tables[&(name, dtz)].as_ref().unwrap();
+        match table.probe(&self.maps, pos, black_stronger, wdl) {
+            Some(v) => v,
+            None => {
+                *state = ProbeState::ChangeStm;
+                0
+            }
+        }
+    }
+
+    // The tables store "don't care" values when the side to move has a winning capture,
+    // and may store a loss instead of a draw reached by a capture. And positions with
+    // en passant rights are not stored at all. So we have to search the captures.
+    // With check_zeroing, we also search the pawn moves, as DTZ does not store these.
+    fn search(&mut self, pos: &Pos, check_zeroing: bool, state: &mut ProbeState) -> i32 {
+        let mut best = WDL_LOSS;
+        let moves = pos.legal_moves();
+        let mut move_count = 0;
+        for &m in &moves {
+            if !pos.is_capture(m) && (!check_zeroing || pos.board[m.0] & 7 != 1) {
+                continue;
+            }
+            move_count += 1;
+            let value = -self.search(&pos.do_move(m), false, state);
+            if *state == ProbeState::Fail {
+                return WDL_DRAW;
+            }
+            if value > best {
+                best = value;
+                if value >= WDL_WIN {
+                    *state = ProbeState::ZeroingBestMove;
+                    return value;
+                }
+            }
+        }
+        let no_more_moves = move_count > 0 && move_count == moves.len();
+        let value = if no_more_moves {
+            best
+        } else {
+            let v = self.probe_table(pos, false, WDL_DRAW, state);
+            if *state == ProbeState::Fail {
+                return WDL_DRAW;
+            }
+            v
+        };
+        if best >= value {
+            *state = if best > WDL_DRAW || no_more_moves {
+                ProbeState::ZeroingBestMove
+            } else {
+                ProbeState::Ok
+            };
+            return best;
+        }
+        *state = ProbeState::Ok;
+        value
+    }
+
+    fn probe_wdl(&mut self, pos: &Pos, state: &mut ProbeState) -> i32 {
+        *state = ProbeState::Ok;
+        self.search(pos, false, state)
+    }
+
+    // distance to zeroing of the 50 move counter in plies, with the sign of the WDL value,
+    // 100 more for cursed wins and blessed losses
+    fn probe_dtz(&mut self, pos: &Pos, state: &mut ProbeState) -> i32 {
+        *state = ProbeState::Ok;
+        let wdl = self.search(pos, true, state);
+        if *state == ProbeState::Fail || wdl == WDL_DRAW {
+            return 0;
+        }
+        if *state == ProbeState::ZeroingBestMove {
+            return dtz_before_zeroing(wdl);
+        }
+        let dtz = self.probe_table(pos, true, wdl, state);
+        if *state == ProbeState::Fail {
+            return 0;
+        }
+        if *state != ProbeState::ChangeStm {
+            let cursed = wdl == WDL_BLESSED_LOSS || wdl == WDL_CURSED_WIN;
+            return (dtz + 100 * cursed as i32) * wdl.signum();
+        }
+        // the table is for the other side to move, so we do a 1 ply search
+        let mut min_dtz = 0xFFFF;
+        for m in pos.legal_moves() {
+            let zeroing = pos.is_zeroing(m);
+            let next = pos.do_move(m);
+            let mut dtz = if zeroing {
+                -dtz_before_zeroing(self.search(&next, false, state))
+            } else {
+                -self.probe_dtz(&next, state)
+            };
+            if dtz == 1 && next.in_check() && next.legal_moves().is_empty() {
+                min_dtz = 1; // a mate
+            }
+            if !zeroing {
+                dtz += dtz.signum();
+            }
+            if dtz < min_dtz && dtz.signum() == wdl.signum() {
+                min_dtz = dtz;
+            }
+            if *state == ProbeState::Fail {
+                return 0;
+            }
+        }
+        if min_dtz == 0xFFFF {
+            -1 // no legal moves, so we are mated
+        } else {
+            min_dtz
+        }
+    }
+}
+
+fn dtz_before_zeroing(wdl: i32) -> i32 {
+    match wdl {
+        WDL_WIN => 1,
+        WDL_CURSED_WIN => 101,
+        WDL_BLESSED_LOSS => -101,
+        WDL_LOSS => -1,
+        _ => 0,
+    }
+}
+
+// ### interface to the engine
+
+fn castling_possible(g: &Game) -> bool {
+    [(0, W_KING, W_ROOK), (1, B_KING, B_ROOK)]
+        .iter()
+        .any(|&(c, king, rook)| {
+            let k = g.castling.king[c] as usize;
+            g.board[k] == king
+                && !g.has_moved.contains(k)
+                && g.castling.rook[c]
+                    .iter()
+                    .any(|&r| g.board[r as usize] == rook && !g.has_moved.contains(r))
+        })
+}
+
+// our columns count from the h-file, so this maps the squares in both directions
+fn mirror_file(p: usize) -> usize {
+    p ^ 7
+}
+
+// the position in the tablebase encoding, or None when it is not covered by the tables
+fn tb_position(g: &Game, color: Color, ep_pos: i8) -> Option<Pos> {
+    let max_pieces = g.tablebases.as_ref()?.max_pieces;
+    if g.variant != Variant::Standard {
+        return None; // the tables are for standard chess
+    }
+    if g.board.iter().filter(|&&f| f != VOID_ID).count() > max_pieces || castling_possible(g) {
+        return None;
+    }
+    let mut pos = Pos {
+        board: [0; 64],
+        stm: (color == COLOR_BLACK) as u8,
+        ep: if ep_pos >= 0 {
+            Some(mirror_file(ep_pos as usize))
+        } else {
+            None
+        },
+    };
+    for (p, &f) in g.board.iter().enumerate() {
+        if f != VOID_ID {
+            pos.board[mirror_file(p)] = if f > 0 { f as u8 } else { 8 + (-f) as u8 };
+        }
+    }
+    Some(pos)
+}
+
+// A WDL probe inside abeta(), with the score for the side to move and the attacked squares,
+// which abeta() uses to test castlings. None when the position is not in the tables,
+// or when the side not to move is in check, so that the king capture is detected by abeta().
+pub(super) fn probe_abeta(
+    g: &mut Game,
+    color: Color,
+    ep_pos: i8,
+    cup: i64,
+) -> Option<(i64, ChessSquares)> {
+    let pos = tb_position(g, color, ep_pos)?;
+    if pos.attacked(pos.king_square(1 - pos.stm), pos.stm) {
+        return None;
+    }
+    let tb = g.tablebases.as_mut()?;
+    let mut state = ProbeState::Ok;
+    let wdl = tb.probe_wdl(&pos, &mut state);
+    if state == ProbeState::Fail {
+        return None;
+    }
+    let score = match wdl {
+        WDL_WIN => TB_WIN_SCORE - cup,
+        WDL_LOSS => -TB_WIN_SCORE + cup,
+        _ => draw_score(g, color), // a draw, or a win or loss which is a draw by the 50 move rule
+    };
+    let mut control = ChessSquares::new();
+    for p in 0..64 {
+        if pos.attacked(mirror_file(p), pos.stm) {
+            control.insert(p);
+        }
+    }
+    Some((score, control))
+}
+
+// The best move by the tablebases for the root position: for a win the one with the shortest
+// distance to zeroing, for a loss the longest one, considering the 50 move rule.
+pub(super) fn root_move(g: &mut Game) -> Option<Move> {
+    let color = ((g.move_counter as i64 + 1) % 2) * 2 - 1;
+    let pos = tb_position(g, color, g.pjm)?;
+    let to_100 = g.to_100 as i32;
+    let tb = g.tablebases.as_mut()?;
+    let mut best: Option<(i32, i32, TbMove)> = None; // rank, dtz, move
+    for m in pos.legal_moves() {
+        if m.2 & 7 == 3 || m.2 & 7 == 4 {
+            continue; // the engine promotes only to queen and knight
+        }
+        let next = pos.do_move(m);
+        let mut state = ProbeState::Ok;
+        let mut dtz = if pos.is_zeroing(m) {
+            dtz_before_zeroing(-tb.probe_wdl(&next, &mut state))
+        } else {
+            let d = -tb.probe_dtz(&next, &mut state);
+            d + d.signum()
+        };
+        if state == ProbeState::Fail {
+            return None;
+        }
+        if dtz == 2 && next.in_check() && next.legal_moves().is_empty() {
+            dtz = 1; // a mate
+        }
+        // wins in time first, then late wins, draws, late losses and losses
+        let rank = if dtz > 0 {
+            if dtz + to_100 <= 100 {
+                3000 - dtz
+            } else {
+                1000 - dtz
+            }
+        } else if dtz < 0 {
+            if -dtz + to_100 <= 100 {
+                -3000 - dtz
+            } else {
+                -1000 - dtz
+            }
+        } else {
+            0
+        };
+        if best.is_none_or(|b| rank > b.0) {
+            best = Some((rank, dtz, m));
+        }
+    }
+    let (_, dtz, m) = best?;
+    let mut uci = move_to_uci(&Move {
+        src: mirror_file(m.0) as i64,
+        dst: mirror_file(m.1) as i64,
+        ..Default::default()
+    });
+    if m.2 != 0 {
+        uci.push(if m.2 & 7 == 5 { 'q' } else { 'n' });
+    }
+    let mut result = move_from_uci(g, &uci).ok()?;
+    result.score = match dtz.signum() {
+        1 => TB_WIN_SCORE - dtz as i64,
+        -1 => -TB_WIN_SCORE - dtz as i64,
+        _ => 0,
+    };
+    if g.verbose {
+        println!("tablebase move {}, dtz {}", uci, dtz);
+    }
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::game;
+    use super::*;
+
+    // tables as after open(), for a directory without the table files
+    fn no_files() -> Tablebases {
+        Tablebases {
+            dir: PathBuf::from("no-such-directory"),
+            max_pieces: TB_PIECES,
+            maps: Maps::new(),
+            tables: HashMap::new(),
+        }
+    }
+
+    fn uci(m: TbMove) -> String {
+        let mut s = move_to_uci(&Move {
+            src: mirror_file(m.0) as i64,
+            dst: mirror_file(m.1) as i64,
+            ..Default::default()
+        });
+        if m.2 != 0 {
+            s.push(['p', 'n', 'b', 'r', 'q'][(m.2 & 7) as usize - 1]);
+        }
+        s
+    }
+
+    #[test]
+    fn index_tables() {
+        let m = Maps::new();
+        assert_eq!(m.b1h1h7.iter().max(), Some(&27));
+        assert_eq!(m.a1d1d4.iter().max(), Some(&9));
+        assert_eq!(m.kk.iter().flatten().max(), Some(&461)); // 462 positions of two kings
+        assert_eq!(m.binomial[2][5], 10);
+        assert_eq!(m.binomial[5][63], 7028847);
+        let mut pawns: Vec<usize> = (8..56).map(|s| m.pawns[s]).collect();
+        pawns.sort();
+        assert_eq!(pawns, (0..48).collect::<Vec<_>>());
+        assert_eq!(m.lead_pawns_size[1], [6; 4]);
+    }
+
+    #[test]
+    fn moves_like_the_engine() {
+        let mut g = game(START_FEN);
+        g.tablebases = Some(no_files());
+        for fen in [
+            "4k3/1P6/8/3pP3/8/8/6p1/R3K3 w - d6 0 1", // promotion and en passant
+            "r3k3/8/8/8/4pP2/8/1p6/4K3 b - f3 0 1",
+            "4k3/4r3/8/8/8/8/4B3/4K3 w - - 0 1", // a pinned bishop
+            "8/8/8/8/8/5k2/6q1/7K w - - 0 1",    // checkmate
+        ] {
+            set_fen(&mut g, fen).unwrap();
+            let color = -(g.move_counter as Color % 2) * 2 + 1;
+            let pos = tb_position(&g, color, g.pjm).unwrap();
+            // the engine promotes only to queen and knight
+            let mut tb: Vec<TbMove> = pos.legal_moves();
+            tb.retain(|m| m.2 & 7 != 3 && m.2 & 7 != 4);
+            let mut moves: Vec<String> = tb.iter().map(|&m| uci(m)).collect();
+            let mut expected: Vec<String> = legal_moves(&mut g)
+                .iter()
+                .map(|el| {
+                    move_to_uci(&Move {
+                        src: el.si as i64,
+                        dst: el.di as i64,
+                        promote_to: el.promote_to as i64,
+                        ..Default::default()
+                    })
+                })
+                .collect();
+            moves.sort();
+            expected.sort();
+            expected.dedup();
+            assert_eq!(moves, expected, "{}", fen);
+            for m in tb {
+                let next = pos.do_move(m);
+                let mv = move_from_uci(&mut g, &uci(m)).unwrap();
+                do_move_promote(&mut g, &mv).unwrap();
+                let after = tb_position(&g, -color, g.pjm).unwrap();
+                assert!(
+                    next.board == after.board && next.stm == after.stm,
+                    "{} {}",
+                    fen,
+                    uci(m)
+                );
+                assert_eq!(next.ep, after.ep, "{} {}", fen, uci(m));
+                set_fen(&mut g, fen).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn not_in_the_tables() {
+        let mut g = game("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1");
+        assert!(root_move(&mut g).is_none()); // no tables loaded
+        assert!(probe_abeta(&mut g, COLOR_WHITE, -1, 1).is_none());
+        g.tablebases = Some(no_files());
+        assert!(castling_possible(&g));
+        assert!(tb_position(&g, COLOR_WHITE, -1).is_none());
+        set_fen(&mut g, "4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        assert!(!castling_possible(&g));
+        assert!(tb_position(&g, COLOR_WHITE, -1).is_some());
+        // the KRvK table is missing
+        assert!(root_move(&mut g).is_none());
+        assert!(probe_abeta(&mut g, COLOR_WHITE, -1, 1).is_none());
+        // KvK needs no table
+        set_fen(&mut g, "4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let (score, _) = probe_abeta(&mut g, COLOR_WHITE, -1, 1).unwrap();
+        assert_eq!(score, draw_score(&g, COLOR_WHITE));
+        assert!(Tablebases::open("no-such-directory").is_err());
+        assert!(Tablebases::open("src").is_err()); // no .rtbw files
+    }
+}
@@ -0,0 +1,163 @@
+// Chess variants with other win conditions, selected at the start of a game with set_variant():
+// King of the Hill, where a king which reaches one of the four center squares wins, and Three-check,
+// where the third check wins. The hooks are after_move() for the moves of the game, lost() at the
+// start of each abeta() node and in game_over(), and evaluate() for the evaluation terms.
+// The checks of Three-check are kept in Game::checks, which encode_board() adds to the position,
+// so the transposition table and the repetition test tell apart positions with other counts.
+
+use super::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Variant {
+    #[default]
+    Standard,
+    KingOfTheHill, // a king on d4, e4, d5 or e5 wins
+    ThreeCheck,    // the third check wins
+}
+
+impl Variant {
+    pub const ALL: [Variant; 3] = [
+        Variant::Standard,
+        Variant::KingOfTheHill,
+        Variant::ThreeCheck,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Variant::Standard => "Standard",
+            Variant::KingOfTheHill => "King of the Hill",
+            Variant::ThreeCheck => "Three-check",
+        }
+    }
+}
+
+// for the command line: standard, kingofthehill or koth, threecheck or 3check
+impl std::str::FromStr for Variant {
+    type Err = EngineError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().replace(['-', '_', ' '], "").as_str() {
+            "standard" => Ok(Variant::Standard),
+            "kingofthehill" | "koth" => Ok(Variant::KingOfTheHill),
+            "threecheck" | "3check" => Ok(Variant::ThreeCheck),
+            _ => Err(EngineError::InvalidParams(format!("unknown variant {}", s))),
+        }
+    }
+}
+
+const HILL: [Position; 4] = [27, 28, 35, 36]; // e4, d4, e5, d5
+
+const HILL_BONUS: [i16; 4] = [300, 120, 40, 10]; // by the distance of the king to the hill
+const CHECK_BONUS: [i16; 4] = [0, 80, 250, 250]; // by the number of checks given
+
+// set the variant and start a new game, set_fen() and reset_game() keep the variant
+pub fn set_variant(g: &mut Game, variant: Variant) {
+    g.variant = variant;
+    reset_game(g);
+}
+
+fn color_index(color: Color) -> usize {
+    (color == COLOR_BLACK) as usize
+}
+
+// after a move of the game, with the side to move next: count the checks of Three-check.
+// A check can not be taken back, so the earlier positions can not repeat.
+pub(super) fn after_move(g: &mut Game, color: Color) {
+    if g.variant == Variant::ThreeCheck && in_check(g, king_pos(g, color), color, true) {
+        g.checks[color_index(opp_color(color))] += 1;
+        g.history.clear();
+    }
+}
+
+// the same in abeta(), after the move of color: true when it gives a check, which is counted for
+// the search of the following moves until uncount_check()
+pub(super) fn count_check(g: &mut Game, color: Color) -> bool {
+    let opp = opp_color(color);
+    let check = g.variant == Variant::ThreeCheck && in_check(g, king_pos(g, opp), opp, true);
+    if check {
+        g.checks[color_index(color)] += 1;
+    }
+    check
+}
+
+pub(super) fn uncount_check(g: &mut Game, color: Color) {
+    g.checks[color_index(color)] -= 1;
+}
+
+// the side to move has lost by the last move of the opponent, with its king on the hill or its
+// third check. A last move which left the king of the opponent in check is illegal instead, which
+// abeta() refutes by the king capture as usual.
+pub(super) fn lost(g: &Game, color: Color) -> bool {
+    let opp = opp_color(color);
+    let won = match g.variant {
+        Variant::Standard => false,
+        Variant::KingOfTheHill => HILL.contains(&king_pos(g, opp)),
+        Variant::ThreeCheck => g.checks[color_index(opp)] >= 3,
+    };
+    won && !in_check(g, king_pos(g, opp), opp, true)
+}
+
+// for game_over()
+pub(super) fn game_over(g: &Game, color: Color) -> Option<GameOver> {
+    if !lost(g, color) {
+        return None;
+    }
+    Some(match g.variant {
+        Variant::KingOfTheHill => GameOver::KingOfTheHill,
+        _ => GameOver::ThreeChecks,
+    })
+}
+
+// the evaluation terms of the variant, for White
+pub(super) fn evaluate(g: &Game) -> i16 {
+    match g.variant {
+        Variant::Standard => 0,
+        Variant::KingOfTheHill => [COLOR_WHITE, COLOR_BLACK]
+            .iter()
+            .map(|&c| {
+                let k = king_pos(g, c);
+                let d = HILL
+                    .iter()
+                    .map(|&h| max((row(k) - row(h)).abs(), (col(k) - col(h)).abs()))
+                    .min()
+                    .unwrap_or(3);
+                HILL_BONUS[d as usize] * c as i16
+            })
+            .sum(),
+        Variant::ThreeCheck => {
+            CHECK_BONUS[min(g.checks[0], 3) as usize] - CHECK_BONUS[min(g.checks[1], 3) as usize]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::game_over;
+    use super::super::tests::game;
+    use super::*;
+
+    #[test]
+    fn variant_wins() {
+        // Ke3-e4 reaches the hill
+        let mut g = game(START_FEN);
+        set_variant(&mut g, Variant::KingOfTheHill);
+        set_fen(&mut g, "k7/8/8/8/8/4K3/8/8 w - - 0 1").unwrap();
+        assert_eq!(game_over(&mut g), None);
+        do_move(&mut g, 19, 27).unwrap();
+        assert_eq!(game_over(&mut g), Some(GameOver::KingOfTheHill));
+        // the same move ends no standard game
+        set_variant(&mut g, Variant::Standard);
+        set_fen(&mut g, "k7/8/8/8/8/4K3/8/8 w - - 0 1").unwrap();
+        do_move(&mut g, 19, 27).unwrap();
+        assert_eq!(game_over(&mut g), Some(GameOver::InsufficientMaterial));
+        // Ra1-a8 is the third check
+        set_variant(&mut g, Variant::ThreeCheck);
+        set_fen(&mut g, "4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        g.checks = [2, 0];
+        assert_eq!(game_over(&mut g), None);
+        do_move(&mut g, 7, 63).unwrap();
+        assert_eq!(g.checks, [3, 0]);
+        assert_eq!(game_over(&mut g), Some(GameOver::ThreeChecks));
+    }
+}
@@ -0,0 +1,183 @@
+// EPD test-suite runner for the tiny Salewski chess engine
+// cargo run --release -- epd suite.epd [--depth n | --time secs]
+//
+// Each line of the file is a position in EPD format: the first four FEN fields, followed by
+// operations separated by semicolons. Supported operations are bm (best moves), am (avoid moves)
+// and id, the moves are in standard algebraic notation, e.g.
+// r1bq2rk/pp3pbp/2p1p1pQ/7P/3P4/2PB1N2/PP3PPR/2KR4 w - - bm Qxh7+; id "WAC.004";
+// A position is solved when the engine plays one of the bm moves and none of the am moves.
+
+use crate::engine;
+use std::time::Instant;
+
+const DEFAULT_SECS_PER_POSITION: f32 = 1.0;
+
+struct Entry {
+    fen: String,
+    id: String,
+    best: Vec<String>,
+    avoid: Vec<String>,
+}
+
+pub fn run(args: &[String]) {
+    let usage = || eprintln!("usage: epd <file> [--depth n | --time secs]");
+    let path = match args.first() {
+        Some(p) => p,
+        None => {
+            usage();
+            return;
+        }
+    };
+    let mut limits = engine::SearchLimits::default();
+    match (arg_value(args, "--depth"), arg_value(args, "--time")) {
+        (Ok(Some(d)), Ok(_)) => limits.max_depth = d,
+        (Ok(None), Ok(t)) => limits.movetime = t.unwrap_or(DEFAULT_SECS_PER_POSITION),
+        _ => {
+            usage();
+            return;
+        }
+    }
+    let text = match std::fs::read_to_string(path) {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            return;
+        }
+    };
+    let mut g = engine::new_game();
+    g.verbose = false;
+    let (mut solved, mut failed, mut skipped) = (0, 0, 0);
+    let start = Instant::now();
+    for (n, line) in text.lines().enumerate() {
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let entry = match parse_line(line) {
+            Some(e) => e,
+            None => {
+                println!("line {}: invalid EPD", n + 1);
+                skipped += 1;
+                continue;
+            }
+        };
+        let id = if entry.id.is_empty() {
+            format!("line {}", n + 1)
+        } else {
+            entry.id.clone()
+        };
+        if let Err(e) = engine::set_fen(&mut g, &entry.fen) {
+            println!("{}: {}", id, e);
+            skipped += 1;
+            continue;
+        }
+        // the expected moves in the notation of the engine
+        let (best, avoid) = match (
+            uci_moves(&mut g, &entry.best),
+            uci_moves(&mut g, &entry.avoid),
+        ) {
+            (Some(b), Some(a)) if !(b.is_empty() && a.is_empty()) => (b, a),
+            _ => {
+                println!("{}: no valid bm or am moves", id);
+                skipped += 1;
+                continue;
+            }
+        };
+        let t = Instant::now();
+        let m = match engine::reply(&mut g, &limits) {
+            Ok(r) => r.best,
+            Err(e) => {
+                println!("{}: {}", id, e);
+                skipped += 1;
+                continue;
+            }
+        };
+        let elapsed = t.elapsed().as_secs_f32();
+        let played = engine::move_to_uci(&m);
+        let ok = (best.is_empty() || best.contains(&played)) && !avoid.contains(&played);
+        if ok {
+            solved += 1;
+        } else {
+            failed += 1;
+        }
+        println!(
+            "{:<20} {:<6} {:<8} {}{}{:7.2} s",
+            id,
+            if ok { "ok" } else { "FAIL" },
+            played,
+            if entry.best.is_empty() {
+                String::new()
+            } else {
+                format!("bm {} ", entry.best.join(" "))
+            },
+            if entry.avoid.is_empty() {
+                String::new()
+            } else {
+                format!("am {} ", entry.avoid.join(" "))
+            },
+            elapsed
+        );
+    }
+    println!(
+        "solved {} of {}, failed {}, skipped {}, total time {:.1} s",
+        solved,
+        solved + failed,
+        failed,
+        skipped,
+        start.elapsed().as_secs_f32()
+    );
+}
+
+// None when the option is missing, Err when its value is missing or malformed
+fn arg_value<T: std::str::FromStr>(args: &[String], name: &str) -> Result<Option<T>, ()> {
+    match args.iter().position(|a| a == name) {
+        None => Ok(None),
+        Some(i) => match args.get(i + 1).map(|v| v.parse()) {
+            Some(Ok(v)) => Ok(Some(v)),
+            _ => Err(()),
+        },
+    }
+}
+
+fn parse_line(line: &str) -> Option<Entry> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() < 4 {
+        return None;
+    }
+    let mut entry = Entry {
+        fen: fields[..4].join(" "),
+        id: String::new(),
+        best: Vec::new(),
+        avoid: Vec::new(),
+    };
+    // the operations follow the fourth field
+    let mut rest = line.trim_start();
+    for _ in 0..4 {
+        rest = rest[rest.find(char::is_whitespace).unwrap_or(rest.len())..].trim_start();
+    }
+    for op in rest.split(';') {
+        let mut words = op.split_whitespace();
+        let opcode = match words.next() {
+            Some(o) => o,
+            None => continue,
+        };
+        let operands: Vec<String> = words.map(|w| w.trim_matches('"').to_string()).collect();
+        match opcode {
+            "bm" => entry.best = operands,
+            "am" => entry.avoid = operands,
+            "id" => entry.id = operands.join(" "),
+            _ => {}
+        }
+    }
+    Some(entry)
+}
+
+// None when one of the moves is not legal in the position
+fn uci_moves(g: &mut engine::Game, san: &[String]) -> Option<Vec<String>> {
+    san.iter()
+        .map(|s| {
+            engine::move_from_san(g, s)
+                .ok()
+                .map(|m| engine::move_to_uci(&m))
+        })
+        .collect()
+}
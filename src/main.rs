@@ -18,14 +18,54 @@ use bevy::{
 
 use bevy_mod_picking::prelude::*;
 use bevy_panorbit_camera::{PanOrbitCamera, PanOrbitCameraPlugin};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 
+mod bench;
 mod engine;
+mod epd;
+mod selfplay;
+mod uci;
 
 const DEFAULT_TIME_PER_MOVE: f32 = 2.0;
 
+// base time in seconds, increment in seconds, moves per period (0 for sudden death)
+// the first entry means no clock, we use the fixed secs per move then.
+const TIME_CONTROLS: [(f32, f32, u16); 4] = [
+    (0.0, 0.0, 0),
+    (300.0, 3.0, 0),
+    (900.0, 10.0, 0),
+    (5400.0, 0.0, 40),
+];
+
+const DEFAULT_BOOK_FILE: &str = "book.bin";
+
+// written by key S and read by key R, unless --load gives another file
+const DEFAULT_SAVE_FILE: &str = "saved_game.json";
+
+// number of lines shown in the analysis overlay
+const ANALYSIS_LINES: usize = 3;
+
+// the longest mate searched by key F, in moves
+const MATE_SEARCH_MOVES: usize = 4;
+
 #[derive(Resource)]
-struct NextMoveTask(Option<Task<engine::Move>>);
+struct NextMoveTask(Option<Task<Result<engine::SearchResult, engine::EngineError>>>);
+
+#[derive(Resource, Default)]
+struct Analysis {
+    enabled: bool,
+    task: Option<Task<Vec<engine::PvLine>>>,
+    done_for: Option<u16>, // move_counter of the analysed position
+}
+
+#[derive(Resource, Default)]
+struct MateSearch {
+    task: Option<Task<Option<Vec<engine::Move>>>>,
+    stop: Arc<AtomicBool>, // of the copy of the game that is searched
+}
 
 #[derive(Component, Reflect, Clone)]
 struct PositionData {
@@ -48,6 +88,9 @@ struct Txt {
     turn: String,
     time: String,
     nxt: String,
+    clock: String,
+    strength: String,
+    analysis: String,
 }
 
 impl Default for Txt {
@@ -56,7 +99,10 @@ impl Default for Txt {
             ui_text: "Left-click on a piece, then on a destination to move. Use the middle mouse\nbutton to rotate the board, right-click to pan, and scroll to zoom.".to_string(),
             turn: "Human player vs. Computer\n  use keypad 1 or 2 to change".to_string(),
             time: format!("{} secs per move\n  use keypad + or - to modify", DEFAULT_TIME_PER_MOVE).to_string(),
-            nxt: "White starts the game".to_string(),
+            nxt: "White starts the game, key N starts a Chess960 game,\n  key V changes the variant".to_string(),
+            clock: "No clock, use key C to select a time control".to_string(),
+            strength: strength_str(engine::MAX_SKILL_LEVEL, [0; 2], [100; 2]),
+            analysis: "Use key A to toggle the analysis, key F to find a mate".to_string(),
         }
     }
 }
@@ -85,6 +131,79 @@ impl Default for SecsPerMove {
     }
 }
 
+#[derive(Resource, Default, Clone, Serialize, Deserialize)]
+struct GameClock {
+    control: usize,      // index into TIME_CONTROLS
+    remaining: [f32; 2], // White, Black
+    moves_done: [u16; 2],
+}
+
+impl GameClock {
+    fn reset(&mut self) {
+        self.remaining = [TIME_CONTROLS[self.control].0; 2];
+        self.moves_done = [0; 2];
+    }
+
+    fn running(&self) -> bool {
+        self.control != 0
+    }
+
+    // side: 0 for White, 1 for Black
+    fn move_done(&mut self, side: usize) {
+        let (base, inc, moves) = TIME_CONTROLS[self.control];
+        self.remaining[side] += inc;
+        self.moves_done[side] += 1;
+        if moves > 0 && self.moves_done[side].is_multiple_of(moves) {
+            self.remaining[side] += base;
+        }
+    }
+
+    fn engine_clock(&self, side: usize) -> Option<engine::Clock> {
+        if !self.running() {
+            return None;
+        }
+        let (_, inc, moves) = TIME_CONTROLS[self.control];
+        Some(engine::Clock {
+            remaining: self.remaining,
+            increment: [inc; 2],
+            moves_to_go: if moves > 0 {
+                moves - self.moves_done[side] % moves
+            } else {
+                0
+            },
+        })
+    }
+}
+
+const CONTEMPT: [i16; 4] = [0, 20, 50, -20];
+const AGGRESSION: [i16; 4] = [100, 150, 200, 50];
+
+fn strength_str(skill_level: u8, contempt: [i16; 2], aggression: [i16; 2]) -> String {
+    format!(
+        "Engine strength {} of {}\n  use key L or shift L to change\n\
+         Contempt {} / {}, aggression {}% / {}%\n  use key T or G for White, with shift for Black",
+        skill_level,
+        engine::MAX_SKILL_LEVEL,
+        contempt[0],
+        contempt[1],
+        aggression[0],
+        aggression[1]
+    )
+}
+
+// the next entry of values after v, or the first one
+fn next_of(values: &[i16], v: i16) -> i16 {
+    match values.iter().position(|&x| x == v) {
+        Some(i) => values[(i + 1) % values.len()],
+        None => values[0],
+    }
+}
+
+fn clock_str(secs: f32) -> String {
+    let secs = secs.max(0.0) as u32;
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
+
 #[derive(Resource, PartialEq)]
 enum State {
     Playing,
@@ -109,12 +228,129 @@ impl Default for GameData {
     }
 }
 
+#[derive(Resource)]
+struct SaveFile {
+    path: String,
+}
+
+// a game saved by key S, with the settings of the players and the clocks
+#[derive(Serialize, Deserialize)]
+struct SavedGame {
+    game: engine::GameRecord,
+    engine_plays: [bool; 2],
+    secs_per_move: f32,
+    clock: GameClock,
+}
+
+// the Polyglot book file, None for no book, else how to select the book moves
+#[derive(Resource)]
+struct OpeningBook {
+    path: String,
+    selection: Option<engine::BookSelection>,
+}
+
 #[derive(Resource, Default)]
 struct SelectionState {
     first_selection: Option<(Entity, PositionData)>,
 }
 
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("uci") {
+        uci::run();
+        return;
+    }
+    // cargo run --release -- bench 8
+    if args.get(1).map(String::as_str) == Some("bench") {
+        bench::run(&args[2..]);
+        return;
+    }
+    // cargo run --release -- epd suite.epd --time 1
+    if args.get(1).map(String::as_str) == Some("epd") {
+        epd::run(&args[2..]);
+        return;
+    }
+    // cargo run --release -- match --games 20 --a a.toml --b b.toml --pgn games.pgn
+    if args.get(1).map(String::as_str) == Some("match") {
+        selfplay::run(&args[2..]);
+        return;
+    }
+    // cargo run --release -- mate 2 "r2qkb1r/pp2nppp/3p4/2pNN1B1/2BnP3/3P4/PPP2PPP/R2bK2R w KQkq - 1 1"
+    if args.get(1).map(String::as_str) == Some("mate") {
+        solve_mate_cli(&args);
+        return;
+    }
+    // cargo run --release -- --book path/to/book.bin
+    let book_arg = arg_value(&args, "--book");
+    let mut book = OpeningBook {
+        path: book_arg.cloned().unwrap_or(DEFAULT_BOOK_FILE.to_string()),
+        selection: None,
+    };
+    let game_data = GameData::default();
+    let mut ep = EnginePlays::default();
+    let mut time = SecsPerMove::default();
+    let mut clock = GameClock::default();
+    let mut txt = Txt::default();
+    // cargo run --release -- --load saved_game.json
+    let save_file = SaveFile {
+        path: arg_value(&args, "--load")
+            .cloned()
+            .unwrap_or(DEFAULT_SAVE_FILE.to_string()),
+    };
+    // cargo run --release -- --variant kingofthehill
+    if let Some(name) = arg_value(&args, "--variant") {
+        match name.parse() {
+            Ok(v) => engine::set_variant(&mut game_data.game.lock().unwrap(), v),
+            Err(e) => eprintln!("Can not select variant: {}", e),
+        }
+    }
+    // cargo run --release -- --chess960 518
+    if let Some(index) = arg_value(&args, "--chess960") {
+        let set = match index.parse() {
+            Ok(i) => engine::chess960_fen(i)
+                .and_then(|fen| engine::set_fen(&mut game_data.game.lock().unwrap(), &fen))
+                .map_err(|e| e.to_string()),
+            Err(_) => Err(format!("invalid index {}", index)),
+        };
+        if let Err(e) = set {
+            eprintln!("Can not start Chess960 game: {}", e);
+        }
+    }
+    if arg_value(&args, "--load").is_some() {
+        if let Err(e) = load_game(
+            &save_file.path,
+            &mut game_data.game.lock().unwrap(),
+            &mut ep,
+            &mut time,
+            &mut clock,
+            &mut txt,
+        ) {
+            eprintln!("Can not load game {}", e);
+        }
+    }
+    if book_arg.is_some() {
+        match engine::open_book(&book.path, engine::BookSelection::Weighted) {
+            Ok(b) => {
+                game_data.game.lock().unwrap().book = Some(b);
+                book.selection = Some(engine::BookSelection::Weighted);
+            }
+            Err(e) => eprintln!("Can not open book {}: {}", book.path, e),
+        }
+    }
+    // cargo run --release -- --params params.toml
+    if let Some(path) = arg_value(&args, "--params") {
+        match engine::load_params(path) {
+            Ok(p) => engine::set_params(&mut game_data.game.lock().unwrap(), p),
+            Err(e) => eprintln!("Can not load parameters {}", e),
+        }
+    }
+    // cargo run --release -- --syzygy path/to/tablebases
+    if let Some(dir) = arg_value(&args, "--syzygy") {
+        match engine::Tablebases::open(dir) {
+            Ok(tb) => game_data.game.lock().unwrap().tablebases = Some(tb),
+            Err(e) => eprintln!("Can not open tablebases {}: {}", dir, e),
+        }
+    }
     App::new()
         .add_plugins(DefaultPlugins.set(WindowPlugin {
             primary_window: Some(Window {
@@ -130,11 +366,16 @@ fn main() {
         .insert_resource(NextMoveTask(None))
         .insert_resource(SelectionState::default())
         .insert_resource(Figure::default())
-        .insert_resource(SecsPerMove::default())
-        .insert_resource(Txt::default())
+        .insert_resource(time)
+        .insert_resource(txt)
         .insert_resource(State::Playing)
-        .insert_resource(GameData::default())
-        .insert_resource(EnginePlays::default())
+        .insert_resource(game_data)
+        .insert_resource(book)
+        .insert_resource(ep)
+        .insert_resource(clock)
+        .insert_resource(save_file)
+        .insert_resource(Analysis::default())
+        .insert_resource(MateSearch::default())
         .add_systems(Startup, setup)
         .add_systems(Startup, setup_menu_text)
         .add_systems(Update, move_figures)
@@ -143,9 +384,45 @@ fn main() {
         .add_systems(Update, text_update_system)
         .add_systems(Update, keyboard_input_system)
         .add_systems(Update, do_engine_move)
+        .add_systems(Update, clock_system)
+        .add_systems(Update, analysis_system)
+        .add_systems(Update, mate_system)
+        .add_systems(Update, save_load_system)
         .run();
 }
 
+// the command line argument following name
+fn arg_value<'a>(args: &'a [String], name: &str) -> Option<&'a String> {
+    args.iter()
+        .position(|a| a == name)
+        .and_then(|i| args.get(i + 1))
+}
+
+// prints the mating line in UCI notation, for the start position when no FEN is given
+fn solve_mate_cli(args: &[String]) {
+    let n: usize = match args.get(2).and_then(|a| a.parse().ok()) {
+        Some(n) if n > 0 => n,
+        _ => {
+            eprintln!("usage: mate <moves> [fen]");
+            return;
+        }
+    };
+    let mut g = engine::new_game();
+    g.verbose = false;
+    let fen = args.get(3).map(String::as_str).unwrap_or(engine::START_FEN);
+    if let Err(e) = engine::set_fen(&mut g, fen) {
+        eprintln!("{}", e);
+        return;
+    }
+    match engine::solve_mate(&mut g, n) {
+        Some(line) => {
+            let moves: Vec<String> = line.iter().map(engine::move_to_uci).collect();
+            println!("mate in {}: {}", line.len().div_ceil(2), moves.join(" "));
+        }
+        None => println!("no mate in {}", n),
+    }
+}
+
 const MAP: [&str; 2] = ["Human", "Computer"];
 
 fn keyboard_input_system(
@@ -153,6 +430,10 @@ fn keyboard_input_system(
     mut t: ResMut<Txt>,
     mut ep: ResMut<EnginePlays>,
     mut time: ResMut<SecsPerMove>,
+    mut clock: ResMut<GameClock>,
+    mut analysis: ResMut<Analysis>,
+    mut mate: ResMut<MateSearch>,
+    mut book: ResMut<OpeningBook>,
     game_data: ResMut<GameData>,
 ) {
     let old_time = time.time;
@@ -180,6 +461,94 @@ fn keyboard_input_system(
             MAP[ep.t[0] as usize], MAP[ep.t[1] as usize]
         );
     }
+    if keyboard_input.just_pressed(KeyCode::KeyC) {
+        // a new time control starts with full clocks
+        clock.control = (clock.control + 1) % TIME_CONTROLS.len();
+        clock.reset();
+        if !clock.running() {
+            t.clock = "No clock, use key C to select a time control".to_string();
+        }
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyA) {
+        analysis.enabled = !analysis.enabled;
+        analysis.done_for = None;
+        if !analysis.enabled {
+            t.analysis = "Use key A to toggle the analysis, key F to find a mate".to_string();
+        }
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyF) {
+        if mate.task.is_some() {
+            mate.stop.store(true, Ordering::Relaxed); // a second key press stops the search
+        } else {
+            // the search may take minutes, so it runs on a copy and the game stays playable
+            let record = engine::game_record(&game_data.game.lock().unwrap());
+            let mut g = engine::new_game();
+            match engine::load_record(&mut g, &record) {
+                Ok(()) => {
+                    analysis.enabled = false;
+                    t.analysis = format!(
+                        "Searching a mate in up to {} moves, key F stops...",
+                        MATE_SEARCH_MOVES
+                    );
+                    mate.stop = g.stop.clone();
+                    mate.task = Some(
+                        AsyncComputeTaskPool::get()
+                            .spawn(async move { engine::solve_mate(&mut g, MATE_SEARCH_MOVES) }),
+                    );
+                }
+                Err(e) => t.analysis = format!("Can not search a mate {}", e),
+            }
+        }
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyB) {
+        // cycle through no book, weighted random and best book moves
+        book.selection = match book.selection {
+            None => Some(engine::BookSelection::Weighted),
+            Some(engine::BookSelection::Weighted) => Some(engine::BookSelection::Best),
+            Some(engine::BookSelection::Best) => None,
+        };
+        let mut g = game_data.game.lock().unwrap();
+        g.book = None;
+        t.ui_text = "Opening book disabled".to_string();
+        if let Some(selection) = book.selection {
+            match engine::open_book(&book.path, selection) {
+                Ok(b) => {
+                    g.book = Some(b);
+                    t.ui_text = format!("Opening book {}: {:?} moves", book.path, selection);
+                }
+                Err(e) => {
+                    book.selection = None;
+                    t.ui_text = format!("Can not open book {}: {}", book.path, e);
+                }
+            }
+        }
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyL) {
+        // L lowers the engine strength, shift L raises it
+        let mut g = game_data.game.lock().unwrap();
+        let raise = keyboard_input.pressed(KeyCode::ShiftLeft)
+            || keyboard_input.pressed(KeyCode::ShiftRight);
+        g.skill_level = if raise {
+            g.skill_level % engine::MAX_SKILL_LEVEL + 1
+        } else {
+            (g.skill_level + engine::MAX_SKILL_LEVEL - 2) % engine::MAX_SKILL_LEVEL + 1
+        };
+        t.strength = strength_str(g.skill_level, g.contempt, g.aggression);
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyT) || keyboard_input.just_pressed(KeyCode::KeyG) {
+        // T cycles the contempt, G the aggression, of White, or with shift of Black
+        let mut g = game_data.game.lock().unwrap();
+        let side = (keyboard_input.pressed(KeyCode::ShiftLeft)
+            || keyboard_input.pressed(KeyCode::ShiftRight)) as usize;
+        let (mut contempt, mut aggression) = (g.contempt, g.aggression);
+        if keyboard_input.just_pressed(KeyCode::KeyT) {
+            contempt[side] = next_of(&CONTEMPT, contempt[side]);
+        } else {
+            aggression[side] = next_of(&AGGRESSION, aggression[side]);
+        }
+        engine::set_style(&mut g, contempt, aggression);
+        t.strength = strength_str(g.skill_level, g.contempt, g.aggression);
+    }
     if keyboard_input.just_pressed(KeyCode::KeyM) {
         engine::print_move_list(&game_data.game.lock().unwrap()); // for engine debugging purpose
     }
@@ -192,16 +561,162 @@ fn new_game(
     mut t: ResMut<Txt>,
     asset_server: Res<AssetServer>,
     mut game_data: ResMut<GameData>,
+    mut clock: ResMut<GameClock>,
+    mut state: ResMut<State>,
+    mut mate: ResMut<MateSearch>,
 ) {
-    if keyboard_input.pressed(KeyCode::Numpad0) {
+    let chess960 = keyboard_input.just_pressed(KeyCode::KeyN);
+    let next_variant = keyboard_input.just_pressed(KeyCode::KeyV);
+    if keyboard_input.pressed(KeyCode::Numpad0) || chess960 || next_variant {
         clear_board(&mut commands, pieces_query);
-        engine::reset_game(&mut game_data.game.lock().unwrap());
+        if mate.task.take().is_some() {
+            mate.stop.store(true, Ordering::Relaxed); // the search is for the old game
+            t.analysis = "Mate search stopped".to_string();
+        }
+        let index = rand::thread_rng().gen_range(0..960);
+        let variant;
+        {
+            let mut g = game_data.game.lock().unwrap();
+            engine::reset_game(&mut g);
+            if next_variant {
+                let all = engine::Variant::ALL;
+                let i = all.iter().position(|&v| v == g.variant).unwrap_or(0);
+                engine::set_variant(&mut g, all[(i + 1) % all.len()]);
+            }
+            variant = g.variant;
+            if chess960 {
+                if let Err(e) =
+                    engine::chess960_fen(index).and_then(|fen| engine::set_fen(&mut g, &fen))
+                {
+                    eprintln!("{}", e); // should not occur, the index is valid
+                }
+            }
+        }
         populate_board(&mut commands, &asset_server, &mut game_data);
-        t.ui_text = "New game".to_string();
+        clock.reset();
+        if *state == State::GameTerminated {
+            *state = State::Playing;
+        }
+        t.ui_text = if chess960 {
+            format!("New Chess960 game, start position {}", index)
+        } else {
+            "New game".to_string()
+        };
+        if variant != engine::Variant::Standard {
+            t.ui_text.push_str(&format!(", variant {}", variant.name()));
+        }
         t.nxt = "White starts the game".to_string();
     }
 }
 
+// key S saves the game, key R restores the saved game
+fn save_load_system(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut commands: Commands,
+    pieces_query: Query<(Entity, &mut Figure)>,
+    asset_server: Res<AssetServer>,
+    mut game_data: ResMut<GameData>,
+    mut ep: ResMut<EnginePlays>,
+    mut time: ResMut<SecsPerMove>,
+    mut clock: ResMut<GameClock>,
+    mut state: ResMut<State>,
+    mut analysis: ResMut<Analysis>,
+    task: Res<NextMoveTask>,
+    save_file: Res<SaveFile>,
+    mut t: ResMut<Txt>,
+) {
+    let save = keyboard_input.just_pressed(KeyCode::KeyS);
+    if !save && !keyboard_input.just_pressed(KeyCode::KeyR) {
+        return;
+    }
+    if task.0.is_some() || analysis.task.is_some() {
+        // the game is locked by the search
+        t.ui_text = "Wait until the engine is done".to_string();
+        return;
+    }
+    if save {
+        t.ui_text = match save_game(
+            &save_file.path,
+            &game_data.game.lock().unwrap(),
+            &ep,
+            &time,
+            &clock,
+        ) {
+            Ok(()) => format!("Game saved to {}", save_file.path),
+            Err(e) => format!("Can not save game {}", e),
+        };
+        return;
+    }
+    let loaded = load_game(
+        &save_file.path,
+        &mut game_data.game.lock().unwrap(),
+        &mut ep,
+        &mut time,
+        &mut clock,
+        &mut t,
+    );
+    if let Err(e) = loaded {
+        t.ui_text = format!("Can not load game {}", e);
+        return;
+    }
+    clear_board(&mut commands, pieces_query);
+    populate_board(&mut commands, &asset_server, &mut game_data);
+    analysis.done_for = None;
+    *state = State::Playing;
+}
+
+fn save_game(
+    path: &str,
+    g: &engine::Game,
+    ep: &EnginePlays,
+    time: &SecsPerMove,
+    clock: &GameClock,
+) -> Result<(), String> {
+    let saved = SavedGame {
+        game: engine::game_record(g),
+        engine_plays: ep.t,
+        secs_per_move: time.time,
+        clock: clock.clone(),
+    };
+    let json = serde_json::to_string_pretty(&saved).map_err(|e| format!("{}: {}", path, e))?;
+    std::fs::write(path, json).map_err(|e| format!("{}: {}", path, e))
+}
+
+// replays the saved game and restores the settings, the game is unchanged on errors
+fn load_game(
+    path: &str,
+    g: &mut engine::Game,
+    ep: &mut EnginePlays,
+    time: &mut SecsPerMove,
+    clock: &mut GameClock,
+    t: &mut Txt,
+) -> Result<(), String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let saved: SavedGame = serde_json::from_str(&text).map_err(|e| format!("{}: {}", path, e))?;
+    if saved.clock.control >= TIME_CONTROLS.len() {
+        return Err(format!("{}: invalid time control", path));
+    }
+    engine::load_record(g, &saved.game).map_err(|e| format!("{}: {}", path, e))?;
+    ep.t = saved.engine_plays;
+    time.time = saved.secs_per_move.clamp(0.3, 5.0);
+    *clock = saved.clock;
+    t.ui_text = format!("Game loaded from {}", path);
+    t.turn = format!(
+        "{} (1) vs {} (2)",
+        MAP[ep.t[0] as usize], MAP[ep.t[1] as usize]
+    );
+    t.time = format!("Secs per move: {:.1}", time.time);
+    t.nxt = format!(
+        "Next move: {}",
+        ["White", "Black"][g.move_counter as usize % 2]
+    );
+    if !clock.running() {
+        t.clock = "No clock, use key C to select a time control".to_string();
+    }
+    t.strength = strength_str(g.skill_level, g.contempt, g.aggression);
+    Ok(())
+}
+
 fn setup_menu_text(mut commands: Commands) {
     commands.spawn((
         TextBundle::from_sections([
@@ -224,6 +739,21 @@ fn setup_menu_text(mut commands: Commands) {
                 color: Color::srgb(0.7, 0.7, 0.7),
                 ..default()
             }),
+            TextSection::from_style(TextStyle {
+                font_size: 24.0,
+                color: Color::srgb(0.7, 0.7, 0.7),
+                ..default()
+            }),
+            TextSection::from_style(TextStyle {
+                font_size: 24.0,
+                color: Color::srgb(0.7, 0.7, 0.7),
+                ..default()
+            }),
+            TextSection::from_style(TextStyle {
+                font_size: 20.0,
+                color: Color::srgb(0.6, 0.8, 0.6),
+                ..default()
+            }),
         ]),
         FpsText,
     ));
@@ -235,6 +765,103 @@ fn text_update_system(t: Res<Txt>, mut query: Query<&mut Text, With<FpsText>>) {
         text.sections[1].value = format!("{}\n", t.turn);
         text.sections[2].value = format!("{}\n", t.time);
         text.sections[3].value = format!("{}\n", t.nxt);
+        text.sections[4].value = format!("{}\n", t.clock);
+        text.sections[5].value = format!("{}\n", t.strength);
+        text.sections[6].value = format!("{}\n", t.analysis);
+    }
+}
+
+// the clock of the side to move runs after the first move of the game
+fn clock_system(
+    timer: Res<Time>,
+    mut clock: ResMut<GameClock>,
+    game_data: Res<GameData>,
+    mut state: ResMut<State>,
+    mut t: ResMut<Txt>,
+) {
+    if !clock.running() || *state == State::GameTerminated {
+        return;
+    }
+    let move_counter = game_data.game.lock().unwrap().move_counter;
+    let side = move_counter as usize % 2;
+    if move_counter > 0 {
+        clock.remaining[side] -= timer.delta_seconds();
+    }
+    t.clock = format!(
+        "White {}  Black {}",
+        clock_str(clock.remaining[0]),
+        clock_str(clock.remaining[1])
+    );
+    if clock.remaining[side] <= 0.0 {
+        t.ui_text = format!(
+            "{} lost on time, game terminated!",
+            ["White", "Black"][side]
+        );
+        t.nxt.clear();
+        *state = State::GameTerminated;
+    }
+}
+
+// the best moves for the human player, computed while the human is to move
+fn analysis_system(
+    mut analysis: ResMut<Analysis>,
+    state: Res<State>,
+    ep: Res<EnginePlays>,
+    time: Res<SecsPerMove>,
+    game_data: Res<GameData>,
+    mut t: ResMut<Txt>,
+) {
+    if let Some(ref mut task) = analysis.task {
+        if let Some(lines) = future::block_on(future::poll_once(task)) {
+            analysis.task = None;
+            if analysis.enabled {
+                t.analysis = lines
+                    .iter()
+                    .enumerate()
+                    .map(|(i, l)| {
+                        let pv: Vec<String> = l.pv.iter().map(engine::move_to_uci).collect();
+                        format!("{}. ({}) {}", i + 1, l.score, pv.join(" "))
+                    })
+                    .collect::<Vec<String>>()
+                    .join("\n");
+            }
+        }
+        return;
+    }
+    if !analysis.enabled || *state != State::Playing {
+        return;
+    }
+    let move_counter = game_data.game.lock().unwrap().move_counter;
+    if ep.t[move_counter as usize % 2] || analysis.done_for == Some(move_counter) {
+        return;
+    }
+    analysis.done_for = Some(move_counter);
+    t.analysis = "Analysing...".to_string();
+    // the game is locked during the analysis, so keep it short
+    let limits = engine::SearchLimits {
+        movetime: time.time / ANALYSIS_LINES as f32,
+        ..Default::default()
+    };
+    let game_clone = game_data.game.clone();
+    analysis.task = Some(AsyncComputeTaskPool::get().spawn(async move {
+        engine::reply_multi_pv(&mut game_clone.lock().unwrap(), &limits, ANALYSIS_LINES)
+    }));
+}
+
+// shows the result of the mate search started by key F
+fn mate_system(mut mate: ResMut<MateSearch>, mut t: ResMut<Txt>) {
+    if let Some(ref mut task) = mate.task {
+        if let Some(line) = future::block_on(future::poll_once(task)) {
+            mate.task = None;
+            t.analysis = match line {
+                Some(line) => {
+                    let moves: Vec<String> = line.iter().map(engine::move_to_uci).collect();
+                    format!("Mate in {}: {}", line.len().div_ceil(2), moves.join(" "))
+                }
+                None if mate.stop.load(Ordering::Relaxed) => "Mate search stopped".to_string(),
+                None => format!("No mate in {} moves", MATE_SEARCH_MOVES),
+            };
+        }
     }
 }
 
@@ -254,9 +881,26 @@ fn do_engine_move(
     mut commands: Commands,
     mut task: ResMut<NextMoveTask>,
     mut position_data_query: Query<&mut PositionData>,
+    mut clock: ResMut<GameClock>,
 ) {
     if let Some(ref mut next_move_task) = task.0 {
-        if let Some(m) = future::block_on(future::poll_once(next_move_task)) {
+        if let Some(result) = future::block_on(future::poll_once(next_move_task)) {
+            if *state == State::GameTerminated {
+                // the engine lost on time while thinking
+                task.0 = None;
+                return;
+            }
+            let (m, stats) = match result {
+                Ok(r) => (r.best, r.stats),
+                Err(e) => {
+                    // checkmate or stalemate
+                    task.0 = None;
+                    t.ui_text = format!("Engine: {}, game terminated!", e);
+                    t.nxt.clear();
+                    *state = State::GameTerminated;
+                    return;
+                }
+            };
             game_data.tagged = [0; 64];
             game_data.tagged[m.src as usize] = 2;
             game_data.tagged[m.dst as usize] = 2;
@@ -270,49 +914,120 @@ fn do_engine_move(
             t.time = format!("Secs per move: {:.1}", time.time);
             let next = game_data.game.lock().unwrap().move_counter as usize % 2;
             t.nxt = format!("Next move: {}", ["Black", "White"][next]);
-            let flag = engine::do_move(
-                &mut game_data.game.lock().unwrap(),
-                m.src as i8,
-                m.dst as i8,
-                false,
-            );
-            t.ui_text = engine::move_to_str(
-                &mut game_data.game.lock().unwrap(),
+            let flag = match engine::do_move_promote(&mut game_data.game.lock().unwrap(), &m) {
+                Ok(flag) => flag,
+                Err(e) => {
+                    task.0 = None;
+                    t.ui_text = format!("Engine: {}, game terminated!", e);
+                    t.nxt.clear();
+                    *state = State::GameTerminated;
+                    return;
+                }
+            };
+            clock.move_done(next);
+            let text = engine::move_to_str(
+                &game_data.game.lock().unwrap(),
                 m.src as i8,
                 m.dst as i8,
                 flag,
-            ) + &format!(" (score: {})", m.score);
-            if m.score == engine::KING_VALUE as i64 {
+            );
+            t.ui_text = text.unwrap_or_else(|e| e.to_string()) + &format!(" (score: {})", m.score);
+            if stats.nodes > 0 {
+                // not for book and tablebase moves
+                t.ui_text.push_str(&format!(
+                    " TT hits {:.0}%, cutoffs {:.0}%",
+                    stats.tt_hit_rate() * 100.0,
+                    stats.cutoff_rate() * 100.0
+                ));
+            }
+            // a variant win scores like a checkmate, it is announced by variant_won() below
+            let over = engine::game_over(&mut game_data.game.lock().unwrap());
+            if over == Some(engine::GameOver::Checkmate) {
                 t.ui_text.push_str(" Checkmate, game terminated!");
                 t.nxt.clear();
                 *state = State::GameTerminated;
                 return;
-            } else if m.score > engine::KING_VALUE_DIV_2 as i64 {
-                t.ui_text.push_str(&format!(
-                    " Checkmate in {}",
-                    (engine::KING_VALUE as i64 - m.score) / 2
-                ));
-            }
-            let x = (7 - m.dst / 8) as f32;
-            let y = (m.dst % 8) as f32;
-            let dst = Vec3::new(x, 0.0, y);
-            for (piece_ent, p) in pieces_query.iter_mut() {
-                if p.location == dst {
-                    commands.entity(piece_ent).despawn();
+            } else if over.is_none() && m.score > engine::KING_VALUE_DIV_2 as i64 {
+                let n = (engine::KING_VALUE as i64 - m.score) / 2;
+                if game_data.game.lock().unwrap().variant == engine::Variant::Standard {
+                    t.ui_text.push_str(&format!(" Checkmate in {}", n));
+                } else {
+                    t.ui_text.push_str(&format!(" Win in {}", n));
                 }
             }
-            let x = (7 - m.src / 8) as f32;
-            let y = (m.src % 8) as f32;
-            let src = Vec3::new(x, 0.0, y);
-            for (_piece_entity, mut piece) in pieces_query.iter_mut() {
-                if piece.location == src {
-                    piece.location = dst;
-                    let pos = position_data_query.get_mut(_piece_entity);
-                    pos.unwrap().location = dst;
+            if flag == engine::FLAG_CASTLING {
+                castle_figures(
+                    &game_data.game.lock().unwrap(),
+                    m.src as i8,
+                    m.dst as i8,
+                    &mut pieces_query,
+                    &mut position_data_query,
+                );
+            } else {
+                let x = (7 - m.dst / 8) as f32;
+                let y = (m.dst % 8) as f32;
+                let dst = Vec3::new(x, 0.0, y);
+                for (piece_ent, p) in pieces_query.iter_mut() {
+                    if p.location == dst {
+                        commands.entity(piece_ent).despawn();
+                    }
+                }
+                let x = (7 - m.src / 8) as f32;
+                let y = (m.src % 8) as f32;
+                let src = Vec3::new(x, 0.0, y);
+                for (_piece_entity, mut piece) in pieces_query.iter_mut() {
+                    if piece.location == src {
+                        piece.location = dst;
+                        let pos = position_data_query.get_mut(_piece_entity);
+                        pos.unwrap().location = dst;
+                    }
                 }
             }
             task.0 = None;
             *state = State::Playing;
+            if let Some(s) = variant_won(&mut game_data.game.lock().unwrap()) {
+                t.ui_text.push_str(s);
+                t.nxt.clear();
+                *state = State::GameTerminated;
+            }
+        }
+    }
+}
+
+// the game end by the win condition of a variant, which leaves the loser with legal moves
+fn variant_won(g: &mut engine::Game) -> Option<&'static str> {
+    match engine::game_over(g)? {
+        engine::GameOver::KingOfTheHill => Some(" King of the Hill, game terminated!"),
+        engine::GameOver::ThreeChecks => Some(" Third check, game terminated!"),
+        _ => None,
+    }
+}
+
+// a castling moves the rook as well, in Chess960 king and rook may even swap their squares
+fn castle_figures(
+    g: &engine::Game,
+    src: i8,
+    dst: i8,
+    pieces_query: &mut Query<(Entity, &mut Figure)>,
+    position_data_query: &mut Query<&mut PositionData>,
+) {
+    let square = |p: i8| Vec3::new((7 - p / 8) as f32, 0.0, (p % 8) as f32);
+    let figures: Vec<(Entity, Vec3)> = engine::castling_moves(g, src, dst)
+        .iter()
+        .flatten()
+        .filter_map(|&(from, to)| {
+            pieces_query
+                .iter()
+                .find(|(_, p)| p.location == square(from))
+                .map(|(e, _)| (e, square(to)))
+        })
+        .collect();
+    for (entity, to) in figures {
+        if let Ok((_, mut piece)) = pieces_query.get_mut(entity) {
+            piece.location = to;
+        }
+        if let Ok(mut pos) = position_data_query.get_mut(entity) {
+            pos.location = to;
         }
     }
 }
@@ -344,17 +1059,22 @@ fn engine(
     ep: Res<EnginePlays>,
     mut state: ResMut<State>,
     game_data: ResMut<GameData>,
+    clock: Res<GameClock>,
 ) {
     if *state == State::Playing {
         let next = game_data.game.lock().unwrap().move_counter as usize % 2;
         if ep.t[next] {
             *state = State::Waiting;
             game_data.game.lock().unwrap().secs_per_move = time.time;
+            game_data.game.lock().unwrap().clock = clock.engine_clock(next);
             if task.0.is_none() {
                 let task_pool = AsyncComputeTaskPool::get();
                 let game_clone = game_data.game.clone();
                 let new_task = task_pool.spawn(async move {
-                    let m = engine::reply(&mut game_clone.lock().unwrap());
+                    let m = engine::reply(
+                        &mut game_clone.lock().unwrap(),
+                        &engine::SearchLimits::default(),
+                    );
                     m
                 });
                 task.0 = Some(new_task);
@@ -369,11 +1089,12 @@ fn process_mouse_click(
     mut commands: Commands,
     time: Res<SecsPerMove>,
     ep: Res<EnginePlays>,
-    state: ResMut<State>,
+    mut state: ResMut<State>,
     mut pieces_query: Query<(Entity, &mut Figure)>,
     game_data: ResMut<GameData>,
     mut t: ResMut<Txt>,
     mut position_data_query: Query<&mut PositionData>,
+    mut clock: ResMut<GameClock>,
 ) {
     if *state == State::Playing {
         let next = game_data.game.lock().unwrap().move_counter as usize % 2;
@@ -396,35 +1117,50 @@ fn process_mouse_click(
                             + first_position_data.location[2] as i8;
                         let b = (7 - position_data.location[0] as i8) * 8
                             + position_data.location[2] as i8;
-                        if !engine::move_is_valid2(
-                            &mut game_data.game.lock().unwrap(),
-                            a as i64,
-                            b as i64,
-                        ) {
-                            t.ui_text = "invalid move, ignored.".to_owned();
-                            selection_state.first_selection = None;
-                            return;
-                        }
+                        let flag = match engine::do_move(&mut game_data.game.lock().unwrap(), a, b)
+                        {
+                            Ok(flag) => flag,
+                            Err(_) => {
+                                t.ui_text = "invalid move, ignored.".to_owned();
+                                selection_state.first_selection = None;
+                                return;
+                            }
+                        };
                         t.turn = format!(
                             "{} (1) vs {} (2)",
                             MAP[ep.t[0] as usize], MAP[ep.t[1] as usize]
                         );
                         t.time = format!("Secs per move: {:.1}", time.time);
                         t.nxt = format!("Next move: {}", ["Black", "White"][next]);
-                        for (piece_entity, mut piece) in pieces_query.iter_mut() {
-                            if piece.location == position_data.location {
-                                commands.entity(piece_entity).despawn();
-                            }
-                            if piece.location == first_position_data.location {
-                                piece.location = position_data.location;
-                                let pos = position_data_query.get_mut(piece_entity);
-                                pos.unwrap().location = position_data.location;
+                        if flag == engine::FLAG_CASTLING {
+                            castle_figures(
+                                &game_data.game.lock().unwrap(),
+                                a,
+                                b,
+                                &mut pieces_query,
+                                &mut position_data_query,
+                            );
+                        } else {
+                            for (piece_entity, mut piece) in pieces_query.iter_mut() {
+                                if piece.location == position_data.location {
+                                    commands.entity(piece_entity).despawn();
+                                }
+                                if piece.location == first_position_data.location {
+                                    piece.location = position_data.location;
+                                    let pos = position_data_query.get_mut(piece_entity);
+                                    pos.unwrap().location = position_data.location;
+                                }
                             }
                         }
-                        let flag =
-                            engine::do_move(&mut game_data.game.lock().unwrap(), a, b, false);
+                        clock.move_done(next);
                         t.ui_text =
-                            engine::move_to_str(&mut game_data.game.lock().unwrap(), a, b, flag);
+                            engine::move_to_str(&mut game_data.game.lock().unwrap(), a, b, flag)
+                                .unwrap_or_else(|e| e.to_string());
+                        if let Some(s) = variant_won(&mut game_data.game.lock().unwrap()) {
+                            t.ui_text.push_str(s);
+                            t.nxt.clear();
+                            *state = State::GameTerminated;
+                        }
                         selection_state.first_selection = None;
                     }
                 }
@@ -532,13 +1268,15 @@ fn populate_board(
     let mat_handle2 = asset_server.load("models/wooden_chess_board.glb#Material0");
     for i in 0..8 {
         for j in 0..8 {
-            let math = if i >= 2 {
+            let f = engine_board[j + i * 8];
+            let math = if f < 0 {
                 mat_handle1.clone()
             } else {
                 mat_handle2.clone()
             };
-            if !(2..=5).contains(&i) {
-                let rotation = if i < 2 {
+            if f != 0 {
+                // any position, e.g. of a restored game
+                let rotation = if f > 0 {
                     Quat::from_rotation_y(std::f32::consts::PI)
                 } else {
                     Quat::from_rotation_y(std::f32::consts::PI * 0.0)
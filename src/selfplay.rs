@@ -0,0 +1,323 @@
+// Headless match between two engine configurations
+// cargo run --release -- match --games 20 --a a.toml --b b.toml [--time secs | --depth n]
+//                              [--openings openings.epd] [--pgn games.pgn] [--variant name]
+//
+// The configurations are parameter files as for --params, a missing one uses the built-in values.
+// Each opening position, given as FEN or EPD lines, is played twice with changed colors; without
+// an opening file all games start from the initial position. The result is reported for A.
+// The variant is one of standard, kingofthehill (koth) or threecheck (3check).
+
+use crate::engine;
+use std::io::Write;
+
+const DEFAULT_GAMES: usize = 10;
+const DEFAULT_SECS_PER_MOVE: f32 = 0.1;
+const MAX_PLIES: usize = 400; // adjudicated as a draw
+
+struct Player {
+    name: String,
+    game: engine::Game,
+}
+
+pub fn run(args: &[String]) {
+    let games: usize = arg_value(args, "--games")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_GAMES);
+    let mut limits = engine::SearchLimits::default();
+    if let Some(d) = arg_value(args, "--depth").and_then(|v| v.parse().ok()) {
+        limits.max_depth = d;
+    } else {
+        limits.movetime = arg_value(args, "--time")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_SECS_PER_MOVE);
+    }
+    let variant: engine::Variant = match arg_value(args, "--variant").map(|v| v.parse()) {
+        None => engine::Variant::Standard,
+        Some(Ok(v)) => v,
+        Some(Err(e)) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    let mut players = Vec::new();
+    for (key, default_name) in [("--a", "A"), ("--b", "B")] {
+        match player(arg_value(args, key), default_name, variant) {
+            Ok(p) => players.push(p),
+            Err(e) => {
+                eprintln!("{}", e);
+                return;
+            }
+        }
+    }
+    let openings: Vec<String> = match arg_value(args, "--openings") {
+        None => vec![engine::START_FEN.to_string()],
+        Some(path) => match std::fs::read_to_string(path) {
+            Ok(text) => text
+                .lines()
+                .filter(|l| !l.trim().is_empty() && !l.starts_with('#'))
+                .map(opening_fen)
+                .collect(),
+            Err(e) => {
+                eprintln!("{}: {}", path, e);
+                return;
+            }
+        },
+    };
+    if openings.is_empty() {
+        eprintln!("no opening positions");
+        return;
+    }
+    let mut pgn = match arg_value(args, "--pgn").map(std::fs::File::create) {
+        None => None,
+        Some(Ok(f)) => Some(f),
+        Some(Err(e)) => {
+            eprintln!("can not create PGN file: {}", e);
+            return;
+        }
+    };
+    let mut wdl = [0; 3]; // wins, draws, losses of A
+    for round in 0..games {
+        let fen = &openings[(round / 2) % openings.len()];
+        let a_white = round % 2 == 0;
+        let (result, reason, moves) = match play(&mut players, a_white, fen, &limits) {
+            Ok(r) => r,
+            Err(e) => {
+                eprintln!("opening {}: {}", fen, e);
+                return;
+            }
+        };
+        let a_score = if a_white { result } else { -result };
+        wdl[(1 - a_score) as usize] += 1;
+        let (white, black) = if a_white { (0, 1) } else { (1, 0) };
+        println!(
+            "game {}: {} - {} {} ({}), A: +{} ={} -{}",
+            round + 1,
+            players[white].name,
+            players[black].name,
+            result_str(result),
+            reason,
+            wdl[0],
+            wdl[1],
+            wdl[2]
+        );
+        if let Some(f) = pgn.as_mut() {
+            let text = pgn_game(
+                round + 1,
+                &players[white].name,
+                &players[black].name,
+                fen,
+                variant,
+                &moves,
+                result,
+            );
+            if let Err(e) = f.write_all(text.as_bytes()) {
+                eprintln!("can not write PGN file: {}", e);
+                pgn = None;
+            }
+        }
+    }
+    let (elo, margin) = elo_difference(wdl);
+    println!(
+        "{} vs {}: +{} ={} -{}, Elo difference {} +/- {} (95%)",
+        players[0].name, players[1].name, wdl[0], wdl[1], wdl[2], elo, margin
+    );
+}
+
+fn arg_value<'a>(args: &'a [String], name: &str) -> Option<&'a String> {
+    args.iter()
+        .position(|a| a == name)
+        .and_then(|i| args.get(i + 1))
+}
+
+// the FEN of a FEN or EPD line, EPD lines have operations instead of the move counters
+fn opening_fen(line: &str) -> String {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let counters = fields.len() >= 6 && fields[4..6].iter().all(|f| f.parse::<u32>().is_ok());
+    let n = if counters { 6 } else { fields.len().min(4) };
+    fields[..n].join(" ")
+}
+
+fn player(
+    path: Option<&String>,
+    default_name: &str,
+    variant: engine::Variant,
+) -> Result<Player, String> {
+    let mut game = engine::new_game();
+    game.verbose = false;
+    engine::set_variant(&mut game, variant);
+    if let Some(path) = path {
+        engine::set_params(
+            &mut game,
+            engine::load_params(path).map_err(|e| e.to_string())?,
+        );
+    }
+    Ok(Player {
+        name: path.cloned().unwrap_or(default_name.to_string()),
+        game,
+    })
+}
+
+// the result for White (1, 0 or -1), the reason, and the moves in SAN
+fn play(
+    players: &mut [Player],
+    a_white: bool,
+    fen: &str,
+    limits: &engine::SearchLimits,
+) -> Result<(i32, String, Vec<String>), String> {
+    for p in players.iter_mut() {
+        engine::set_fen(&mut p.game, fen).map_err(|e| e.to_string())?;
+    }
+    let mut moves = Vec::new();
+    loop {
+        let side = players[0].game.move_counter as usize % 2; // 0 for White
+        if let Some(over) = engine::game_over(&mut players[0].game) {
+            let result = if over.lost() {
+                if side == 0 {
+                    -1
+                } else {
+                    1
+                }
+            } else {
+                0
+            };
+            return Ok((result, format!("{:?}", over), moves));
+        }
+        if moves.len() >= MAX_PLIES {
+            return Ok((0, "adjudicated".to_string(), moves));
+        }
+        let mover = if (side == 0) == a_white { 0 } else { 1 };
+        let m = engine::reply(&mut players[mover].game, limits)
+            .map_err(|e| e.to_string())?
+            .best;
+        moves.push(engine::move_to_san(&mut players[0].game, &m).map_err(|e| e.to_string())?);
+        for p in players.iter_mut() {
+            engine::do_move_promote(&mut p.game, &m).map_err(|e| e.to_string())?;
+        }
+    }
+}
+
+fn result_str(result: i32) -> &'static str {
+    match result {
+        1 => "1-0",
+        -1 => "0-1",
+        _ => "1/2-1/2",
+    }
+}
+
+fn pgn_game(
+    round: usize,
+    white: &str,
+    black: &str,
+    fen: &str,
+    variant: engine::Variant,
+    moves: &[String],
+    result: i32,
+) -> String {
+    let mut text = String::new();
+    text.push_str("[Event \"Engine match\"]\n[Site \"?\"]\n[Date \"????.??.??\"]\n");
+    text.push_str(&format!("[Round \"{}\"]\n", round));
+    text.push_str(&format!("[White \"{}\"]\n[Black \"{}\"]\n", white, black));
+    text.push_str(&format!("[Result \"{}\"]\n", result_str(result)));
+    if fen != engine::START_FEN {
+        text.push_str(&format!("[SetUp \"1\"]\n[FEN \"{}\"]\n", fen));
+    }
+    if variant != engine::Variant::Standard {
+        text.push_str(&format!("[Variant \"{}\"]\n", variant.name()));
+    }
+    text.push('\n');
+    // move numbers as in the FEN
+    let fields: Vec<&str> = fen.split_whitespace().collect();
+    let black_first = fields.get(1) == Some(&"b");
+    let mut number: usize = fields.get(5).and_then(|n| n.parse().ok()).unwrap_or(1);
+    let mut line = String::new();
+    for (i, m) in moves.iter().enumerate() {
+        let white_move = (i % 2 == 0) != black_first;
+        let mut token = String::new();
+        if white_move {
+            token = format!("{}. ", number);
+        } else if i == 0 {
+            token = format!("{}... ", number);
+        }
+        token.push_str(m);
+        if !white_move {
+            number += 1;
+        }
+        if line.len() + token.len() + 1 > 80 {
+            text.push_str(line.trim_end());
+            text.push('\n');
+            line.clear();
+        }
+        line.push_str(&token);
+        line.push(' ');
+    }
+    line.push_str(result_str(result));
+    text.push_str(&line);
+    text.push_str("\n\n");
+    text
+}
+
+// Elo difference of the score, and the margin of its 95% confidence interval
+fn elo_difference(wdl: [u32; 3]) -> (String, String) {
+    let n = (wdl[0] + wdl[1] + wdl[2]) as f64;
+    if n == 0.0 {
+        return ("0".to_string(), "inf".to_string());
+    }
+    let score = (wdl[0] as f64 + wdl[1] as f64 * 0.5) / n;
+    let variance = (wdl[0] as f64 * (1.0 - score).powi(2)
+        + wdl[1] as f64 * (0.5 - score).powi(2)
+        + wdl[2] as f64 * score.powi(2))
+        / n;
+    let deviation = 1.96 * (variance / n).sqrt();
+    let elo = |s: f64| -400.0 * (1.0 / s - 1.0).log10();
+    if score <= 0.0 || score >= 1.0 {
+        let sign = if score <= 0.0 { "-" } else { "+" };
+        return (format!("{}inf", sign), "inf".to_string());
+    }
+    let low = elo((score - deviation).max(1e-6));
+    let high = elo((score + deviation).min(1.0 - 1e-6));
+    let diff = elo(score).round();
+    (
+        format!("{:.0}", if diff == 0.0 { 0.0 } else { diff }), // no -0
+        format!("{:.0}", (high - low) / 2.0),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn elo_differences() {
+        assert_eq!(elo_difference([0, 0, 0]).0, "0");
+        assert_eq!(elo_difference([5, 2, 5]).0, "0");
+        assert_eq!(elo_difference([499, 1, 500]).0, "0"); // -0.35
+        assert_eq!(elo_difference([3, 0, 1]).0, "191");
+        assert_eq!(elo_difference([1, 0, 3]).0, "-191");
+        assert_eq!(
+            elo_difference([2, 0, 0]),
+            ("+inf".to_string(), "inf".to_string())
+        );
+        assert_eq!(elo_difference([0, 0, 2]).0, "-inf");
+    }
+
+    #[test]
+    fn pgn_move_numbers() {
+        let moves = ["e5", "Nf3", "Nc6"].map(String::from);
+        let fen = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 12";
+        let text = pgn_game(1, "a", "b", fen, engine::Variant::Standard, &moves, 0);
+        assert!(text.contains("[FEN \"rnbqkbnr/"));
+        assert!(text.ends_with("\n12... e5 13. Nf3 Nc6 1/2-1/2\n\n"));
+        let moves = ["e4", "e5", "Nf3"].map(String::from);
+        let text = pgn_game(
+            2,
+            "a",
+            "b",
+            engine::START_FEN,
+            engine::Variant::Standard,
+            &moves,
+            1,
+        );
+        assert!(!text.contains("[FEN"));
+        assert!(text.ends_with("\n1. e4 e5 2. Nf3 1-0\n\n"));
+    }
+}
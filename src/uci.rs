@@ -0,0 +1,349 @@
+// Minimal UCI frontend for the tiny Salewski chess engine
+// cargo run --release -- uci
+//
+// Supported: uci, isready, ucinewgame, setoption, position, go, stop, quit
+// The search runs in its own thread, so that stop and isready are handled while searching.
+
+use crate::engine;
+use std::io::BufRead;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+const MAX_MULTI_PV: usize = 5;
+const DEFAULT_BOOK_FILE: &str = "book.bin";
+
+pub fn run() {
+    let game = Arc::new(Mutex::new(engine::new_game()));
+    let stop = game.lock().unwrap().stop.clone();
+    {
+        let mut g = game.lock().unwrap();
+        g.verbose = false;
+        g.progress = Some(Box::new(print_info));
+    }
+    let mut multi_pv: usize = 1;
+    let mut own_book = false;
+    let mut book_file = DEFAULT_BOOK_FILE.to_string();
+    let mut book_selection = engine::BookSelection::Weighted;
+    let mut skill_level = engine::MAX_SKILL_LEVEL;
+    let mut limit_strength = false;
+    let mut elo = engine::MAX_ELO;
+    let mut chess960 = false; // castlings as king takes rook, even for the standard start position
+    let mut search: Option<thread::JoinHandle<()>> = None;
+    for line in std::io::stdin().lock().lines() {
+        let line = match line {
+            Ok(l) => l,
+            Err(_) => break,
+        };
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.first() {
+            Some(&"uci") => {
+                println!("id name Salewski Chess");
+                println!("id author Dr. Stefan Salewski");
+                println!(
+                    "option name MultiPV type spin default 1 min 1 max {}",
+                    MAX_MULTI_PV
+                );
+                println!("option name OwnBook type check default false");
+                println!(
+                    "option name BookFile type string default {}",
+                    DEFAULT_BOOK_FILE
+                );
+                println!(
+                    "option name BookSelection type combo default Weighted var Weighted var Best"
+                );
+                println!("option name SyzygyPath type string default <empty>");
+                println!("option name ParamFile type string default <empty>");
+                println!(
+                    "option name Skill Level type spin default {} min 1 max {}",
+                    engine::MAX_SKILL_LEVEL,
+                    engine::MAX_SKILL_LEVEL
+                );
+                println!("option name UCI_LimitStrength type check default false");
+                println!(
+                    "option name UCI_Elo type spin default {} min {} max {}",
+                    engine::MAX_ELO,
+                    engine::MIN_ELO,
+                    engine::MAX_ELO
+                );
+                println!(
+                    "option name Contempt type spin default 0 min {} max {}",
+                    -engine::MAX_CONTEMPT,
+                    engine::MAX_CONTEMPT
+                );
+                println!(
+                    "option name Aggression type spin default 100 min 0 max {}",
+                    engine::MAX_AGGRESSION
+                );
+                println!("option name UCI_Chess960 type check default false");
+                println!("option name UCI_Variant type combo default standard var standard var kingofthehill var 3check");
+                println!("uciok");
+            }
+            Some(&"isready") => println!("readyok"),
+            Some(&"setoption") => {
+                if let (Some(name), Some(value)) = (
+                    option_value(&tokens, "name"),
+                    option_value(&tokens, "value"),
+                ) {
+                    let name = name.to_ascii_lowercase();
+                    match name.as_str() {
+                        "multipv" => multi_pv = value.parse().unwrap_or(1).clamp(1, MAX_MULTI_PV),
+                        "ownbook" => own_book = value == "true",
+                        "bookfile" => book_file = value,
+                        "syzygypath" => {
+                            wait_for(&mut search);
+                            let mut g = game.lock().unwrap();
+                            g.tablebases = None;
+                            if value != "<empty>" {
+                                match engine::Tablebases::open(&value) {
+                                    Ok(tb) => {
+                                        println!(
+                                            "info string found tablebases with up to {} pieces",
+                                            tb.max_pieces()
+                                        );
+                                        g.tablebases = Some(tb);
+                                    }
+                                    Err(e) => println!("info string {}", e),
+                                }
+                            }
+                        }
+                        "paramfile" => {
+                            wait_for(&mut search);
+                            let params = if value == "<empty>" {
+                                Ok(engine::EvalParams::default())
+                            } else {
+                                engine::load_params(&value)
+                            };
+                            match params {
+                                Ok(p) => engine::set_params(&mut game.lock().unwrap(), p),
+                                Err(e) => println!("info string {}", e),
+                            }
+                        }
+                        "bookselection" => {
+                            book_selection = if value.eq_ignore_ascii_case("Best") {
+                                engine::BookSelection::Best
+                            } else {
+                                engine::BookSelection::Weighted
+                            }
+                        }
+                        "skill level" => {
+                            skill_level = value
+                                .parse()
+                                .unwrap_or(engine::MAX_SKILL_LEVEL)
+                                .clamp(1, engine::MAX_SKILL_LEVEL)
+                        }
+                        "uci_limitstrength" => limit_strength = value == "true",
+                        "uci_elo" => elo = value.parse().unwrap_or(engine::MAX_ELO),
+                        "uci_chess960" => chess960 = value == "true",
+                        "uci_variant" => {
+                            wait_for(&mut search);
+                            match value.parse() {
+                                Ok(v) => engine::set_variant(&mut game.lock().unwrap(), v),
+                                Err(e) => println!("info string {}", e),
+                            }
+                        }
+                        "contempt" | "aggression" => {
+                            wait_for(&mut search);
+                            let mut g = game.lock().unwrap();
+                            let (mut contempt, mut aggression) = (g.contempt, g.aggression);
+                            if name == "contempt" {
+                                contempt = [value
+                                    .parse()
+                                    .unwrap_or(0i16)
+                                    .clamp(-engine::MAX_CONTEMPT, engine::MAX_CONTEMPT);
+                                    2];
+                            } else {
+                                aggression = [value
+                                    .parse()
+                                    .unwrap_or(100i16)
+                                    .clamp(0, engine::MAX_AGGRESSION);
+                                    2];
+                            }
+                            engine::set_style(&mut g, contempt, aggression);
+                        }
+                        _ => {}
+                    }
+                    if name.contains("skill") || name.starts_with("uci_") {
+                        wait_for(&mut search);
+                        game.lock().unwrap().skill_level = if limit_strength {
+                            engine::skill_level_from_elo(elo)
+                        } else {
+                            skill_level
+                        };
+                    }
+                    if name.contains("book") {
+                        wait_for(&mut search);
+                        let mut g = game.lock().unwrap();
+                        g.book = None;
+                        if own_book {
+                            match engine::open_book(&book_file, book_selection) {
+                                Ok(book) => g.book = Some(book),
+                                Err(e) => {
+                                    println!("info string can not open book {}: {}", book_file, e)
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            Some(&"ucinewgame") => {
+                wait_for(&mut search);
+                engine::reset_game(&mut game.lock().unwrap());
+            }
+            Some(&"position") => {
+                wait_for(&mut search);
+                if let Err(e) = set_position(&mut game.lock().unwrap(), &tokens, chess960) {
+                    println!("info string {}", e);
+                }
+            }
+            Some(&"go") => {
+                wait_for(&mut search);
+                let (limits, clock) = parse_go(&tokens);
+                stop.store(false, Ordering::Relaxed);
+                let game = game.clone();
+                let stop = stop.clone();
+                search = Some(thread::spawn(move || {
+                    let mut g = game.lock().unwrap();
+                    g.clock = clock;
+                    let m = if multi_pv > 1 {
+                        engine::reply_multi_pv(&mut g, &limits, multi_pv)
+                            .first()
+                            .map(|l| l.pv[0])
+                            .unwrap_or_default()
+                    } else {
+                        engine::reply(&mut g, &limits)
+                            .map(|r| r.best)
+                            .unwrap_or_default()
+                    };
+                    // in infinite mode, bestmove is sent only after the stop command
+                    while limits.infinite && !stop.load(Ordering::Relaxed) {
+                        thread::sleep(Duration::from_millis(5));
+                    }
+                    if m.src == m.dst {
+                        println!("bestmove 0000"); // checkmate or stalemate
+                    } else {
+                        println!("bestmove {}", engine::move_to_uci(&m));
+                    }
+                }));
+            }
+            Some(&"stop") => {
+                stop.store(true, Ordering::Relaxed);
+                wait_for(&mut search);
+            }
+            Some(&"quit") => {
+                stop.store(true, Ordering::Relaxed);
+                wait_for(&mut search);
+                break;
+            }
+            _ => {}
+        }
+    }
+}
+
+fn wait_for(search: &mut Option<thread::JoinHandle<()>>) {
+    if let Some(handle) = search.take() {
+        handle.join().unwrap();
+    }
+}
+
+// the words following key up to the next keyword, e.g. the option name
+fn option_value(tokens: &[&str], key: &str) -> Option<String> {
+    let start = tokens.iter().position(|&t| t == key)? + 1;
+    let words: Vec<&str> = tokens[start..]
+        .iter()
+        .take_while(|&&t| t != "name" && t != "value")
+        .copied()
+        .collect();
+    if words.is_empty() {
+        None
+    } else {
+        Some(words.join(" "))
+    }
+}
+
+// a FEN with Chess960 castling squares or Shredder-FEN castling files selects Chess960 castlings as well
+fn set_position(g: &mut engine::Game, tokens: &[&str], chess960: bool) -> Result<(), String> {
+    let moves_start = tokens.iter().position(|&t| t == "moves");
+    let end = moves_start.unwrap_or(tokens.len());
+    match tokens.get(1) {
+        Some(&"startpos") => engine::set_fen(g, engine::START_FEN).map_err(|e| e.to_string())?,
+        Some(&"fen") => engine::set_fen(g, &tokens[2..end].join(" ")).map_err(|e| e.to_string())?,
+        _ => return Err("position needs startpos or fen".to_string()),
+    }
+    g.chess960 |= chess960;
+    if let Some(start) = moves_start {
+        for s in &tokens[start + 1..] {
+            let m = engine::move_from_uci(g, s).map_err(|e| e.to_string())?;
+            engine::do_move_promote(g, &m).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+fn parse_go(tokens: &[&str]) -> (engine::SearchLimits, Option<engine::Clock>) {
+    let mut limits = engine::SearchLimits::default();
+    let mut clock = engine::Clock::default();
+    let mut has_clock = false;
+    let mut i = 1;
+    while i < tokens.len() {
+        let value = tokens
+            .get(i + 1)
+            .and_then(|v| v.parse::<f32>().ok())
+            .unwrap_or(0.0);
+        match tokens[i] {
+            "wtime" => {
+                clock.remaining[0] = value / 1000.0;
+                has_clock = true;
+            }
+            "btime" => {
+                clock.remaining[1] = value / 1000.0;
+                has_clock = true;
+            }
+            "winc" => clock.increment[0] = value / 1000.0,
+            "binc" => clock.increment[1] = value / 1000.0,
+            "movestogo" => clock.moves_to_go = value as u16,
+            "depth" => limits.max_depth = value as usize,
+            "nodes" => limits.max_nodes = value as u64,
+            "movetime" => limits.movetime = value / 1000.0,
+            "mate" => limits.mate = value as usize,
+            "infinite" => {
+                limits.infinite = true;
+                i -= 1; // no value
+            }
+            _ => i -= 1,
+        }
+        i += 2;
+    }
+    (limits, if has_clock { Some(clock) } else { None })
+}
+
+fn score_str(score: i64) -> String {
+    if score > engine::KING_VALUE_DIV_2 as i64 {
+        format!("mate {}", (engine::KING_VALUE as i64 - score) / 2 + 1)
+    } else if score < -engine::KING_VALUE_DIV_2 as i64 {
+        format!("mate -{}", (engine::KING_VALUE as i64 + score + 1) / 2)
+    } else {
+        format!("cp {}", score)
+    }
+}
+
+fn print_info(info: &engine::SearchInfo) {
+    let pv: Vec<String> = info.pv.iter().map(engine::move_to_uci).collect();
+    let millis = info.time.as_millis().max(1);
+    println!(
+        "info depth {} multipv {} score {} nodes {} nps {} time {} pv {}",
+        info.depth,
+        info.multi_pv,
+        score_str(info.score),
+        info.nodes,
+        info.nodes as u128 * 1000 / millis,
+        millis,
+        pv.join(" ")
+    );
+    println!(
+        "info string tt hits {:.1}% cutoffs {:.1}%",
+        info.stats.tt_hit_rate() * 100.0,
+        info.stats.cutoff_rate() * 100.0
+    );
+}